@@ -0,0 +1,311 @@
+use crate::constants;
+
+use lazy_static::lazy_static;
+use log::{debug, warn};
+use std::env;
+use std::error::Error;
+use std::ffi::OsString;
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::sync::RwLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+lazy_static! {
+    // Timeout of external commands in seconds, must be set before the first scrape
+    static ref COMMAND_TIMEOUT: RwLock<u64> = RwLock::new(constants::DEFAULT_COMMAND_TIMEOUT);
+}
+
+pub fn set_command_timeout(timeout: u64) {
+    let mut t = match COMMAND_TIMEOUT.write() {
+        Ok(v) => v,
+        Err(e) => e.into_inner(),
+    };
+    *t = timeout;
+}
+
+fn command_timeout() -> u64 {
+    match COMMAND_TIMEOUT.read() {
+        Ok(v) => *v,
+        Err(e) => *e.into_inner(),
+    }
+}
+
+pub fn run_command(cmd: &str, args: &[String]) -> Result<String, Box<dyn Error>> {
+    let (rc, stdout) = run_command_status(cmd, args)?;
+    if rc != 0 {
+        bail!("{} command exited with non-normal exit code {}", cmd, rc);
+    }
+    Ok(stdout)
+}
+
+// Like run_command but returns the exit code instead of failing for commands like
+// scontrol ping which report useful information on non-zero exit codes
+pub fn run_command_status(cmd: &str, args: &[String]) -> Result<(i32, String), Box<dyn Error>> {
+    run_command_retries(cmd, args, constants::COMMAND_RETRIES)
+}
+
+// Like run_command_status but without retries, e.g. if the runtime of the command
+// itself is measured
+pub fn run_command_status_once(
+    cmd: &str,
+    args: &[String],
+) -> Result<(i32, String), Box<dyn Error>> {
+    run_command_retries(cmd, args, 0)
+}
+
+fn run_command_retries(
+    cmd: &str,
+    args: &[String],
+    retries: u32,
+) -> Result<(i32, String), Box<dyn Error>> {
+    let mut backoff = constants::COMMAND_RETRY_BACKOFF;
+    let mut retry: u32 = 0;
+
+    loop {
+        debug!(
+            "command.rs:run_command_retries: running external command: {} {}",
+            cmd,
+            args.join(" ")
+        );
+        let (rc, stdout, stderr) = execute(cmd, args, command_timeout())?;
+        debug!(
+            "command.rs:run_command_retries: external command {} finished with exit code {}",
+            cmd, rc
+        );
+
+        for line in stderr.lines() {
+            if rc == 0 {
+                debug!("stderr of {} command: {}", cmd, line);
+            } else {
+                warn!("stderr of {} command: {}", cmd, line);
+            }
+        }
+
+        if rc != 0 && retry < retries && is_transient_error(&stderr) {
+            retry += 1;
+            warn!(
+                "{} command failed with a transient error, retrying in {} seconds ({}/{})",
+                cmd, backoff, retry, retries
+            );
+            thread::sleep(Duration::from_secs(backoff));
+            backoff *= 2;
+            continue;
+        }
+        return Ok((rc, stdout));
+    }
+}
+
+fn is_format_variable(key: &str) -> bool {
+    !constants::COMMAND_ENVIRONMENT_KEEP.contains(&key)
+        && constants::COMMAND_ENVIRONMENT_REMOVE_PREFIXES
+            .iter()
+            .any(|p| key.starts_with(p))
+}
+
+fn is_transient_error(stderr: &str) -> bool {
+    constants::COMMAND_TRANSIENT_ERRORS
+        .iter()
+        .any(|e| stderr.contains(e))
+}
+
+// Set the locale and remove the format variables (of the variable names vars) for the child
+// only, changing the environment of the process is not safe while other threads are running
+fn build_command<I: Iterator<Item = OsString>>(cmd: &str, args: &[String], vars: I) -> Command {
+    let mut command = Command::new(cmd);
+    command.args(args).env("LANG", "C").env("LC_ALL", "C");
+    for key in vars {
+        if is_format_variable(&key.to_string_lossy()) {
+            command.env_remove(key);
+        }
+    }
+    command
+}
+
+// Run cmd, killing it after timeout seconds
+fn execute(
+    cmd: &str,
+    args: &[String],
+    timeout: u64,
+) -> Result<(i32, String, String), Box<dyn Error>> {
+    let mut command = build_command(cmd, args, env::vars_os().map(|(k, _)| k));
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Pipes are drained by separate threads, otherwise a child writing more than the pipe
+    // buffer would block forever
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(v) = child.try_wait()? {
+            break v;
+        }
+        if start.elapsed() >= Duration::from_secs(timeout) {
+            if let Err(e) = child.kill() {
+                warn!("Can't kill {} command: {}", cmd, e);
+            }
+            let _ = child.wait();
+            bail!("{} command timed out after {} seconds", cmd, timeout);
+        }
+        thread::sleep(Duration::from_millis(constants::COMMAND_POLL_INTERVAL));
+    };
+
+    let stdout = match stdout.join() {
+        Ok(v) => v?,
+        Err(_) => bail!("Can't read standard output of {} command", cmd),
+    };
+    let stderr = match stderr.join() {
+        Ok(v) => v?,
+        Err(_) => bail!("Can't read standard error of {} command", cmd),
+    };
+
+    if stdout.len() > constants::MAX_COMMAND_OUTPUT {
+        bail!(
+            "Output of {} command exceeds {} bytes",
+            cmd,
+            constants::MAX_COMMAND_OUTPUT
+        );
+    }
+
+    let rc = match status.code() {
+        Some(v) => v,
+        None => {
+            bail!("Can't get return code of {} command", cmd);
+        }
+    };
+
+    Ok((
+        rc,
+        String::from_utf8(stdout)?,
+        String::from_utf8_lossy(&stderr).to_string(),
+    ))
+}
+
+// Read at most MAX_COMMAND_OUTPUT + 1 bytes, closing the pipe early makes the child
+// terminate on its next write
+fn read_pipe<R: Read + Send + 'static>(
+    pipe: Option<R>,
+) -> thread::JoinHandle<std::io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut buffer: Vec<u8> = Vec::new();
+        if let Some(p) = pipe {
+            p.take(constants::MAX_COMMAND_OUTPUT as u64 + 1)
+                .read_to_end(&mut buffer)?;
+        }
+        Ok(buffer)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    fn shell(script: &str) -> Vec<String> {
+        vec!["-c".to_string(), script.to_string()]
+    }
+
+    // Script appending a line to marker for every execution before failing with message
+    fn counting_failure(marker: &std::path::Path, message: &str) -> Vec<String> {
+        shell(&format!(
+            "echo run >> {}; echo '{}' >&2; exit 1",
+            marker.display(),
+            message
+        ))
+    }
+
+    fn executions(marker: &std::path::Path) -> usize {
+        let count = match std::fs::read_to_string(marker) {
+            Ok(v) => v.lines().count(),
+            Err(_) => 0,
+        };
+        let _ = std::fs::remove_file(marker);
+        count
+    }
+
+    fn marker(name: &str) -> std::path::PathBuf {
+        let marker =
+            env::temp_dir().join(format!("slurm-exporter-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+        marker
+    }
+
+    #[test]
+    fn test_timeout_kills_command() {
+        let start = Instant::now();
+        let result = execute("sh", &shell("sleep 30"), 1);
+
+        assert!(result.unwrap_err().to_string().contains("timed out"));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_transient_error_is_retried() {
+        let marker = marker("retry");
+        let script = format!(
+            "if [ -e {0} ]; then echo ok; else touch {0}; echo '{1}' >&2; exit 1; fi",
+            marker.display(),
+            constants::COMMAND_TRANSIENT_ERRORS[0]
+        );
+
+        let result = run_command_status("sh", &shell(&script));
+        let _ = std::fs::remove_file(&marker);
+
+        assert_eq!(result.unwrap(), (0, "ok\n".to_string()));
+    }
+
+    #[test]
+    fn test_failure_is_not_retried() {
+        let marker = marker("failure");
+        let args = counting_failure(&marker, "scontrol: error: Invalid user for SlurmUser");
+
+        let (rc, _) = run_command_status("sh", &args).unwrap();
+        assert_eq!(rc, 1);
+        assert_eq!(executions(&marker), 1);
+    }
+
+    #[test]
+    fn test_transient_error_is_not_retried_once() {
+        let marker = marker("once");
+        let args = counting_failure(&marker, constants::COMMAND_TRANSIENT_ERRORS[0]);
+
+        let (rc, _) = run_command_status_once("sh", &args).unwrap();
+        assert_eq!(rc, 1);
+        assert_eq!(executions(&marker), 1);
+    }
+
+    #[test]
+    fn test_output_size_is_capped() {
+        let script = format!("head -c {} /dev/zero", constants::MAX_COMMAND_OUTPUT + 1);
+        let result = run_command_status("sh", &shell(&script));
+
+        assert!(result.unwrap_err().to_string().contains("exceeds"));
+    }
+
+    #[test]
+    fn test_format_variables_are_removed() {
+        assert!(is_format_variable("SLURM_TIME_FORMAT"));
+        assert!(is_format_variable("SQUEUE_FORMAT"));
+        assert!(is_format_variable("SINFO_FORMAT"));
+        assert!(is_format_variable("SPRIO_FORMAT"));
+        assert!(!is_format_variable("SLURM_CONF"));
+        assert!(!is_format_variable("PATH"));
+
+        let vars = ["SLURM_TIME_FORMAT", "SQUEUE_FORMAT", "SLURM_CONF", "PATH"];
+        let command = build_command("squeue", &[], vars.iter().map(OsString::from));
+        let envs: Vec<(&OsStr, Option<&OsStr>)> = command.get_envs().collect();
+        for key in ["SLURM_TIME_FORMAT", "SQUEUE_FORMAT"] {
+            assert!(envs.contains(&(OsStr::new(key), None)));
+        }
+        for key in ["SLURM_CONF", "PATH"] {
+            assert!(!envs.iter().any(|(k, _)| *k == key));
+        }
+        assert!(envs.contains(&(OsStr::new("LC_ALL"), Some(OsStr::new("C")))));
+    }
+}
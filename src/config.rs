@@ -0,0 +1,15 @@
+use std::collections::HashMap;
+
+#[derive(Clone, Debug)]
+pub struct Configuration {
+    pub clusters: String,
+    pub bitmask: u32,
+    // in seconds
+    pub time_limit_threshold: i64,
+    // in seconds, 0 collects metrics on every scrape
+    pub background_interval: u64,
+    // in seconds, minimal age of the last collection before a scrape triggers a new one
+    pub min_interval: i64,
+    // in seconds, collectors without refresh interval run on every collection
+    pub collector_intervals: HashMap<String, i64>,
+}
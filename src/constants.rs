@@ -4,21 +4,362 @@ pub const DEFAULT_LISTEN_ADDRESS: &str = "localhost:9703";
 pub const DEFAULT_METRICS_PATH: &str = "metrics";
 pub const ROOT_HTML: &str = "<html>\n<head><title>SLURM exporter</title></head>\n<body>\n<h1>SLURM exporter</h1>\n<p><a href=\"/metric\">Metrics</a></p>\n</body>\n</html>\n";
 pub const SLURM_CLUSTERS: &str = "all";
+// in seconds, the list of cluster names for "all" is resolved again after this time
+pub const CLUSTER_NAMES_CACHE_TIME: i64 = 300;
+pub const DEFAULT_JOB_LABELS: &str = "partition";
+// in minutes
+pub const DEFAULT_TIME_LIMIT_THRESHOLD: i64 = 15;
+// in seconds
+pub const DEFAULT_ACCOUNTING_INTERVAL: i64 = 300;
+// in seconds, the time window of sacct queries ends this long before the query because
+// slurmctld sends finished jobs to slurmdbd asynchronously
+pub const ACCOUNTING_DELAY: i64 = 60;
+// number of background collection intervals without finished collection before the
+// served metrics are considered stale
+pub const STALE_COLLECTION_INTERVALS: i64 = 2;
+// in seconds, collectors with a refresh interval are considered due this early to
+// compensate for scheduling jitter of the collection
+pub const COLLECTOR_INTERVAL_SLACK: i64 = 1;
+// in seconds
+pub const DEFAULT_COMMAND_TIMEOUT: u64 = 60;
+// in seconds, doubled for every retry
+pub const COMMAND_RETRY_BACKOFF: u64 = 1;
+pub const COMMAND_RETRIES: u32 = 2;
+// in milliseconds
+pub const COMMAND_POLL_INTERVAL: u64 = 10;
+// in bytes
+pub const MAX_COMMAND_OUTPUT: usize = 64 * 1024 * 1024;
+// Errors of SLURM commands worth a retry, usually caused by a busy slurmctld
+pub const COMMAND_TRANSIENT_ERRORS: &[&str] = &[
+    "Socket timed out on send/recv operation",
+    "Unable to contact slurm controller",
+    "Zero Bytes were transmitted or received",
+    "Resource temporarily unavailable",
+];
+// Environment variables of SLURM commands with these prefixes change the output format
+// (e.g. SLURM_TIME_FORMAT, SQUEUE_FORMAT) and are not passed to the commands
+pub const COMMAND_ENVIRONMENT_REMOVE_PREFIXES: &[&str] =
+    &["SACCT_", "SINFO_", "SLURM_", "SPRIO_", "SQUEUE_", "SSHARE_"];
+// Environment variables passed to the commands regardless of their prefix
+pub const COMMAND_ENVIRONMENT_KEEP: &[&str] = &["SLURM_CONF"];
+pub const SUPPORTED_JOB_LABELS: &[&str] = &["account", "partition", "qos", "user"];
 
 pub const METRIC_PARTITIONS_NAME: &str = "partition_states";
 pub const METRIC_PARTITIONS_HELP: &str = "State of partitions of each cluster";
 
+pub const METRIC_PARTITION_CPUS_ALLOCATED_NAME: &str = "slurm_partition_cpus_allocated";
+pub const METRIC_PARTITION_CPUS_ALLOCATED_HELP: &str = "SLURM partitions: number of allocated CPUs";
+pub const METRIC_PARTITION_CPUS_IDLE_NAME: &str = "slurm_partition_cpus_idle";
+pub const METRIC_PARTITION_CPUS_IDLE_HELP: &str = "SLURM partitions: number of idle CPUs";
+pub const METRIC_PARTITION_CPUS_OTHER_NAME: &str = "slurm_partition_cpus_other";
+pub const METRIC_PARTITION_CPUS_OTHER_HELP: &str =
+    "SLURM partitions: number of CPUs in other state (e.g. down or drained)";
+pub const METRIC_PARTITION_CPUS_TOTAL_NAME: &str = "slurm_partition_cpus_total";
+pub const METRIC_PARTITION_CPUS_TOTAL_HELP: &str = "SLURM partitions: number of configured CPUs";
+
+pub const METRIC_PARTITION_STATE_NAME: &str = "slurm_partition_state";
+pub const METRIC_PARTITION_STATE_HELP: &str = "SLURM partitions: configured state of partition";
+pub const PARTITION_STATES: &[&str] = &["UP", "DOWN", "DRAIN", "INACTIVE"];
+pub const METRIC_PARTITION_DEFAULT_NAME: &str = "slurm_partition_default";
+pub const METRIC_PARTITION_DEFAULT_HELP: &str =
+    "SLURM partitions: partition is the default partition";
+pub const METRIC_PARTITION_NODES_CONFIGURED_NAME: &str = "slurm_partition_nodes_configured";
+pub const METRIC_PARTITION_NODES_CONFIGURED_HELP: &str =
+    "SLURM partitions: number of nodes assigned to partition";
+pub const METRIC_PARTITION_CPUS_CONFIGURED_NAME: &str = "slurm_partition_cpus_configured";
+pub const METRIC_PARTITION_CPUS_CONFIGURED_HELP: &str =
+    "SLURM partitions: number of CPUs assigned to partition";
+pub const METRIC_PARTITION_MAX_TIME_NAME: &str = "slurm_partition_max_time_seconds";
+pub const METRIC_PARTITION_MAX_TIME_HELP: &str = "SLURM partitions: maximum time limit of jobs";
+pub const METRIC_PARTITION_DEFAULT_TIME_NAME: &str = "slurm_partition_default_time_seconds";
+pub const METRIC_PARTITION_DEFAULT_TIME_HELP: &str = "SLURM partitions: default time limit of jobs";
+pub const METRIC_PARTITION_MAX_NODES_NAME: &str = "slurm_partition_max_nodes";
+pub const METRIC_PARTITION_MAX_NODES_HELP: &str =
+    "SLURM partitions: maximum number of nodes allocated to a job";
+pub const METRIC_PARTITION_PRIORITY_TIER_NAME: &str = "slurm_partition_priority_tier";
+pub const METRIC_PARTITION_PRIORITY_TIER_HELP: &str =
+    "SLURM partitions: priority tier of partition";
+pub const METRIC_PARTITION_PREEMPT_MODE_NAME: &str = "slurm_partition_preempt_mode";
+pub const METRIC_PARTITION_PREEMPT_MODE_HELP: &str =
+    "SLURM partitions: preemption mode of partition";
+
+pub const METRIC_PARTITION_GRES_CONFIGURED_NAME: &str = "slurm_partition_gres_configured";
+pub const METRIC_PARTITION_GRES_CONFIGURED_HELP: &str =
+    "SLURM partitions: number of configured generic resources";
+pub const METRIC_PARTITION_GRES_ALLOCATED_NAME: &str = "slurm_partition_gres_allocated";
+pub const METRIC_PARTITION_GRES_ALLOCATED_HELP: &str =
+    "SLURM partitions: number of allocated generic resources";
+pub const METRIC_PARTITION_GRES_IDLE_NAME: &str = "slurm_partition_gres_idle";
+pub const METRIC_PARTITION_GRES_IDLE_HELP: &str =
+    "SLURM partitions: number of idle generic resources";
+
+pub const METRIC_NODE_STATE_NAME: &str = "slurm_node_state";
+pub const METRIC_NODE_STATE_HELP: &str = "SLURM nodes: state of node in partition";
+pub const METRIC_NODE_CPUS_ALLOCATED_NAME: &str = "slurm_node_cpus_allocated";
+pub const METRIC_NODE_CPUS_ALLOCATED_HELP: &str = "SLURM nodes: number of allocated CPUs";
+pub const METRIC_NODE_CPUS_IDLE_NAME: &str = "slurm_node_cpus_idle";
+pub const METRIC_NODE_CPUS_IDLE_HELP: &str = "SLURM nodes: number of idle CPUs";
+pub const METRIC_NODE_CPUS_OTHER_NAME: &str = "slurm_node_cpus_other";
+pub const METRIC_NODE_CPUS_OTHER_HELP: &str =
+    "SLURM nodes: number of CPUs in other state (e.g. down or drained)";
+pub const METRIC_NODE_CPUS_TOTAL_NAME: &str = "slurm_node_cpus_total";
+pub const METRIC_NODE_CPUS_TOTAL_HELP: &str = "SLURM nodes: number of configured CPUs";
+pub const METRIC_NODE_MEMORY_NAME: &str = "slurm_node_memory_bytes";
+pub const METRIC_NODE_MEMORY_HELP: &str = "SLURM nodes: real memory in bytes";
+pub const METRIC_NODE_MEMORY_ALLOCATED_NAME: &str = "slurm_node_memory_allocated_bytes";
+pub const METRIC_NODE_MEMORY_ALLOCATED_HELP: &str = "SLURM nodes: allocated memory in bytes";
+pub const METRIC_NODE_MEMORY_FREE_NAME: &str = "slurm_node_memory_free_bytes";
+pub const METRIC_NODE_MEMORY_FREE_HELP: &str = "SLURM nodes: free memory in bytes";
+pub const METRIC_NODE_CPU_LOAD_NAME: &str = "slurm_node_cpu_load";
+pub const METRIC_NODE_CPU_LOAD_HELP: &str = "SLURM nodes: CPU load as reported by the node";
+
+pub const METRIC_SCHEDULER_SERVER_THREADS_NAME: &str = "slurm_scheduler_server_threads";
+pub const METRIC_SCHEDULER_SERVER_THREADS_HELP: &str = "SLURM scheduler: number of server threads";
+pub const METRIC_SCHEDULER_AGENT_QUEUE_SIZE_NAME: &str = "slurm_scheduler_agent_queue_size";
+pub const METRIC_SCHEDULER_AGENT_QUEUE_SIZE_HELP: &str =
+    "SLURM scheduler: number of enqueued outgoing RPC requests";
+pub const METRIC_SCHEDULER_AGENT_COUNT_NAME: &str = "slurm_scheduler_agent_count";
+pub const METRIC_SCHEDULER_AGENT_COUNT_HELP: &str = "SLURM scheduler: number of agent threads";
+pub const METRIC_SCHEDULER_AGENT_THREADS_NAME: &str = "slurm_scheduler_agent_threads";
+pub const METRIC_SCHEDULER_AGENT_THREADS_HELP: &str =
+    "SLURM scheduler: total number of active threads created by all agent threads";
+pub const METRIC_SCHEDULER_DBD_AGENT_QUEUE_SIZE_NAME: &str = "slurm_scheduler_dbd_agent_queue_size";
+pub const METRIC_SCHEDULER_DBD_AGENT_QUEUE_SIZE_HELP: &str =
+    "SLURM scheduler: number of messages queued for SlurmDBD";
+pub const METRIC_SCHEDULER_CYCLE_LAST_NAME: &str = "slurm_scheduler_cycle_last_seconds";
+pub const METRIC_SCHEDULER_CYCLE_LAST_HELP: &str =
+    "SLURM scheduler: time of the last scheduling cycle";
+pub const METRIC_SCHEDULER_CYCLE_MAX_NAME: &str = "slurm_scheduler_cycle_max_seconds";
+pub const METRIC_SCHEDULER_CYCLE_MAX_HELP: &str =
+    "SLURM scheduler: maximal time of a scheduling cycle";
+pub const METRIC_SCHEDULER_CYCLE_MEAN_NAME: &str = "slurm_scheduler_cycle_mean_seconds";
+pub const METRIC_SCHEDULER_CYCLE_MEAN_HELP: &str =
+    "SLURM scheduler: mean time of scheduling cycles";
+pub const METRIC_SCHEDULER_DEPTH_MEAN_NAME: &str = "slurm_scheduler_depth_mean";
+pub const METRIC_SCHEDULER_DEPTH_MEAN_HELP: &str =
+    "SLURM scheduler: mean number of jobs processed in a scheduling cycle";
+pub const METRIC_SCHEDULER_BACKFILL_DEPTH_LAST_NAME: &str = "slurm_scheduler_backfill_depth_last";
+pub const METRIC_SCHEDULER_BACKFILL_DEPTH_LAST_HELP: &str =
+    "SLURM scheduler: number of jobs processed during the last backfill cycle";
+pub const METRIC_SCHEDULER_BACKFILLED_JOBS_NAME: &str = "slurm_scheduler_backfilled_jobs";
+pub const METRIC_SCHEDULER_BACKFILLED_JOBS_HELP: &str =
+    "SLURM scheduler: number of jobs started by backfilling since last SLURM start";
+pub const METRIC_SCHEDULER_BACKFILLED_JOBS_SINCE_CYCLE_NAME: &str =
+    "slurm_scheduler_backfilled_jobs_since_cycle";
+pub const METRIC_SCHEDULER_BACKFILLED_JOBS_SINCE_CYCLE_HELP: &str =
+    "SLURM scheduler: number of jobs started by backfilling since last statistics cycle start";
+pub const METRIC_RPC_TYPE_COUNT_NAME: &str = "slurm_rpc_type_count";
+pub const METRIC_RPC_TYPE_COUNT_HELP: &str = "SLURM RPC: number of RPCs by message type";
+pub const METRIC_RPC_TYPE_AVG_TIME_NAME: &str = "slurm_rpc_type_avg_time_seconds";
+pub const METRIC_RPC_TYPE_AVG_TIME_HELP: &str = "SLURM RPC: average time of RPCs by message type";
+pub const METRIC_RPC_TYPE_TOTAL_TIME_NAME: &str = "slurm_rpc_type_total_time_seconds";
+pub const METRIC_RPC_TYPE_TOTAL_TIME_HELP: &str = "SLURM RPC: total time of RPCs by message type";
+pub const METRIC_RPC_USER_COUNT_NAME: &str = "slurm_rpc_user_count";
+pub const METRIC_RPC_USER_COUNT_HELP: &str = "SLURM RPC: number of RPCs by user";
+pub const METRIC_RPC_USER_AVG_TIME_NAME: &str = "slurm_rpc_user_avg_time_seconds";
+pub const METRIC_RPC_USER_AVG_TIME_HELP: &str = "SLURM RPC: average time of RPCs by user";
+pub const METRIC_RPC_USER_TOTAL_TIME_NAME: &str = "slurm_rpc_user_total_time_seconds";
+pub const METRIC_RPC_USER_TOTAL_TIME_HELP: &str = "SLURM RPC: total time of RPCs by user";
+
+pub const METRIC_SHARE_RAW_SHARES_NAME: &str = "slurm_share_raw_shares";
+pub const METRIC_SHARE_RAW_SHARES_HELP: &str =
+    "SLURM fairshare: raw shares assigned to account or user";
+pub const METRIC_SHARE_NORM_SHARES_NAME: &str = "slurm_share_norm_shares";
+pub const METRIC_SHARE_NORM_SHARES_HELP: &str =
+    "SLURM fairshare: normalised shares assigned to account or user";
+pub const METRIC_SHARE_RAW_USAGE_NAME: &str = "slurm_share_raw_usage";
+pub const METRIC_SHARE_RAW_USAGE_HELP: &str = "SLURM fairshare: raw usage of account or user";
+pub const METRIC_SHARE_EFFECTIVE_USAGE_NAME: &str = "slurm_share_effective_usage";
+pub const METRIC_SHARE_EFFECTIVE_USAGE_HELP: &str =
+    "SLURM fairshare: effective usage of account or user";
+pub const METRIC_SHARE_FAIRSHARE_NAME: &str = "slurm_share_fairshare_factor";
+pub const METRIC_SHARE_FAIRSHARE_HELP: &str =
+    "SLURM fairshare: fairshare factor of account or user";
+
+pub const METRIC_JOBS_FINISHED_NAME: &str = "slurm_job_finished_total";
+pub const METRIC_JOBS_FINISHED_HELP: &str =
+    "SLURM accounting: number of finished jobs by final state and exit code class";
+pub const METRIC_JOBS_FINISHED_CPU_SECONDS_NAME: &str = "slurm_job_finished_cpu_seconds_total";
+pub const METRIC_JOBS_FINISHED_CPU_SECONDS_HELP: &str =
+    "SLURM accounting: CPU time consumed by finished jobs";
+
+pub const METRIC_JOBS_PRIORITY_NAME: &str = "slurm_job_priority";
+pub const METRIC_JOBS_PRIORITY_HELP: &str =
+    "SLURM jobs: quantiles of weighted priority and priority components of pending jobs";
+pub const PRIORITY_QUANTILES: &[f64] = &[0.0, 0.25, 0.5, 0.75, 0.9, 1.0];
+
+pub const METRIC_CONTROLLER_UP_NAME: &str = "slurm_controller_up";
+pub const METRIC_CONTROLLER_UP_HELP: &str = "SLURM controllers: controller is responding";
+pub const METRIC_CONTROLLER_PING_DURATION_NAME: &str = "slurm_controller_ping_duration_seconds";
+pub const METRIC_CONTROLLER_PING_DURATION_HELP: &str =
+    "SLURM controllers: duration of scontrol ping in seconds, includes timeouts of unreachable controllers";
+
+pub const METRIC_RESERVATION_NODES_NAME: &str = "slurm_reservation_nodes";
+pub const METRIC_RESERVATION_NODES_HELP: &str = "SLURM reservations: number of reserved nodes";
+pub const METRIC_RESERVATION_CORES_NAME: &str = "slurm_reservation_cores";
+pub const METRIC_RESERVATION_CORES_HELP: &str = "SLURM reservations: number of reserved cores";
+pub const METRIC_RESERVATION_ACTIVE_NAME: &str = "slurm_reservation_active";
+pub const METRIC_RESERVATION_ACTIVE_HELP: &str = "SLURM reservations: reservation is active";
+pub const METRIC_RESERVATION_SECONDS_UNTIL_START_NAME: &str =
+    "slurm_reservation_seconds_until_start";
+pub const METRIC_RESERVATION_SECONDS_UNTIL_START_HELP: &str =
+    "SLURM reservations: seconds until start of reservation, negative if already started";
+pub const METRIC_RESERVATION_SECONDS_UNTIL_END_NAME: &str = "slurm_reservation_seconds_until_end";
+pub const METRIC_RESERVATION_SECONDS_UNTIL_END_HELP: &str =
+    "SLURM reservations: seconds until end of reservation";
+pub const METRIC_RESERVATION_FLAGS_NAME: &str = "slurm_reservation_flag";
+pub const METRIC_RESERVATION_FLAGS_HELP: &str = "SLURM reservations: flags set for reservation";
+pub const METRIC_MAINTENANCE_ACTIVE_NAME: &str = "slurm_maintenance_active";
+pub const METRIC_MAINTENANCE_ACTIVE_HELP: &str =
+    "SLURM reservations: an active reservation with MAINT flag exists";
+
+pub const METRIC_NODE_REASON_NAME: &str = "slurm_node_reason_info";
+pub const METRIC_NODE_REASON_HELP: &str =
+    "SLURM nodes: reason and user who set it for drained, down or failed nodes";
+pub const METRIC_NODE_REASON_SECONDS_NAME: &str = "slurm_node_reason_age_seconds";
+pub const METRIC_NODE_REASON_SECONDS_HELP: &str =
+    "SLURM nodes: seconds since the reason for drained, down or failed nodes was set";
+
+pub const METRIC_LICENSE_TOTAL_NAME: &str = "slurm_license_total";
+pub const METRIC_LICENSE_TOTAL_HELP: &str = "SLURM licenses: number of configured licenses";
+pub const METRIC_LICENSE_USED_NAME: &str = "slurm_license_used";
+pub const METRIC_LICENSE_USED_HELP: &str = "SLURM licenses: number of licenses in use";
+pub const METRIC_LICENSE_FREE_NAME: &str = "slurm_license_free";
+pub const METRIC_LICENSE_FREE_HELP: &str = "SLURM licenses: number of available licenses";
+pub const METRIC_LICENSE_RESERVED_NAME: &str = "slurm_license_reserved";
+pub const METRIC_LICENSE_RESERVED_HELP: &str =
+    "SLURM licenses: number of licenses reserved by reservations";
+pub const METRIC_LICENSE_PENDING_JOBS_NAME: &str = "slurm_license_pending_jobs";
+pub const METRIC_LICENSE_PENDING_JOBS_HELP: &str =
+    "SLURM licenses: number of pending jobs waiting for licenses";
+pub const METRIC_LICENSE_REMOTE_COUNT_NAME: &str = "slurm_license_remote_count";
+pub const METRIC_LICENSE_REMOTE_COUNT_HELP: &str =
+    "SLURM licenses: number of remote licenses stored in the SLURM database";
+pub const METRIC_LICENSE_REMOTE_ALLOCATED_NAME: &str = "slurm_license_remote_allocated_percent";
+pub const METRIC_LICENSE_REMOTE_ALLOCATED_HELP: &str =
+    "SLURM licenses: percentage of remote licenses allocated to clusters";
+
 pub const METRIC_JOBS_NODES_NAME: &str = "slurm_job_nodes";
 pub const METRIC_JOBS_NODES_HELP: &str = "SLURM jobs: number of allocated or requested nodes";
 pub const METRIC_JOBS_TASKS_NAME: &str = "slurm_job_tasks";
 pub const METRIC_JOBS_TASKS_HELP: &str = "SLURM jobs: number of allocated or requested tasks";
 pub const METRIC_JOBS_CPUS_NAME: &str = "slurm_job_cpus";
 pub const METRIC_JOBS_CPUS_HELP: &str = "SLURM jobs: number of allocated or requested CPUs";
+pub const METRIC_JOBS_PENDING_GRES_NAME: &str = "slurm_job_pending_gres";
+pub const METRIC_JOBS_PENDING_GRES_HELP: &str =
+    "SLURM jobs: number of generic resources requested by pending jobs";
+pub const METRIC_JOBS_PENDING_COUNT_NAME: &str = "slurm_job_pending_count";
+pub const METRIC_JOBS_PENDING_COUNT_HELP: &str = "Number of pending SLURM jobs by pending reason";
+pub const METRIC_JOBS_PENDING_CPUS_NAME: &str = "slurm_job_pending_cpus";
+pub const METRIC_JOBS_PENDING_CPUS_HELP: &str =
+    "SLURM jobs: number of CPUs requested by pending jobs by pending reason";
+pub const METRIC_JOBS_PENDING_NODES_NAME: &str = "slurm_job_pending_nodes";
+pub const METRIC_JOBS_PENDING_NODES_HELP: &str =
+    "SLURM jobs: number of nodes requested by pending jobs by pending reason";
+pub const METRIC_JOBS_PENDING_WAIT_NAME: &str = "slurm_job_pending_wait_seconds";
+pub const METRIC_JOBS_PENDING_WAIT_HELP: &str =
+    "SLURM jobs: time pending jobs have been waiting since submission (snapshot of currently pending jobs, not cumulative)";
+pub const METRIC_JOBS_PENDING_ELIGIBLE_WAIT_NAME: &str = "slurm_job_pending_eligible_wait_seconds";
+pub const METRIC_JOBS_PENDING_ELIGIBLE_WAIT_HELP: &str =
+    "SLURM jobs: time pending jobs have been waiting since becoming eligible (snapshot of currently pending jobs, not cumulative)";
+pub const METRIC_JOBS_PENDING_OLDEST_ELIGIBLE_NAME: &str =
+    "slurm_job_pending_oldest_eligible_seconds";
+pub const METRIC_JOBS_PENDING_OLDEST_ELIGIBLE_HELP: &str =
+    "SLURM jobs: wait time of the longest waiting eligible pending job";
+pub const WAIT_TIME_BUCKETS: &[f64] = &[
+    60.0, 300.0, 900.0, 1800.0, 3600.0, 7200.0, 14400.0, 28800.0, 86400.0, 172800.0, 604800.0,
+];
+pub const METRIC_JOBS_RUNNING_ELAPSED_NAME: &str = "slurm_job_running_elapsed_seconds";
+pub const METRIC_JOBS_RUNNING_ELAPSED_HELP: &str =
+    "SLURM jobs: elapsed time of running jobs (snapshot of currently running jobs, not cumulative)";
+pub const METRIC_JOBS_RUNNING_TIME_LIMIT_RATIO_NAME: &str = "slurm_job_running_time_limit_ratio";
+pub const METRIC_JOBS_RUNNING_TIME_LIMIT_RATIO_HELP: &str =
+    "SLURM jobs: ratio of elapsed time and time limit of running jobs (snapshot of currently running jobs, not cumulative)";
+pub const METRIC_JOBS_RUNNING_NEAR_TIME_LIMIT_NAME: &str = "slurm_job_running_near_time_limit";
+pub const METRIC_JOBS_RUNNING_NEAR_TIME_LIMIT_HELP: &str =
+    "SLURM jobs: number of running jobs within the configured threshold of their time limit";
+pub const RUNTIME_BUCKETS: &[f64] = &[
+    300.0, 900.0, 1800.0, 3600.0, 7200.0, 14400.0, 28800.0, 43200.0, 86400.0, 172800.0, 345600.0,
+    604800.0,
+];
+pub const TIME_LIMIT_RATIO_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 0.75, 0.9, 0.95, 1.0];
 pub const METRIC_JOBS_COUNT_NAME: &str = "slurm_job_count";
 pub const METRIC_JOBS_COUNT_HELP: &str = "Number of SLURM jobs in a given state";
+pub const METRIC_JOBS_ARRAY_JOBS_PENDING_NAME: &str = "slurm_job_array_jobs_pending";
+pub const METRIC_JOBS_ARRAY_JOBS_PENDING_HELP: &str =
+    "SLURM jobs: number of array jobs with pending array tasks";
+pub const METRIC_JOBS_ARRAY_TASKS_PENDING_NAME: &str = "slurm_job_array_tasks_pending";
+pub const METRIC_JOBS_ARRAY_TASKS_PENDING_HELP: &str = "SLURM jobs: number of pending array tasks";
+
+pub const METRIC_COLLECTOR_SUCCESS_NAME: &str = "slurm_exporter_collector_success";
+pub const METRIC_COLLECTOR_SUCCESS_HELP: &str = "Exporter: collector finished successfully";
+pub const METRIC_COLLECTOR_DURATION_NAME: &str = "slurm_exporter_collector_duration_seconds";
+pub const METRIC_COLLECTOR_DURATION_HELP: &str = "Exporter: duration of collector run in seconds";
+pub const METRIC_COLLECTOR_LAST_UPDATE_NAME: &str =
+    "slurm_exporter_collector_last_update_timestamp_seconds";
+pub const METRIC_COLLECTOR_LAST_UPDATE_HELP: &str =
+    "Exporter: UNIX timestamp of the last successful collector run";
+pub const METRIC_LAST_COLLECTION_NAME: &str = "slurm_exporter_last_collection_timestamp_seconds";
+pub const METRIC_LAST_COLLECTION_HELP: &str =
+    "Exporter: UNIX timestamp of the collection served by this scrape";
+pub const METRIC_COLLECTION_STALE_NAME: &str = "slurm_exporter_collection_stale";
+pub const METRIC_COLLECTION_STALE_HELP: &str =
+    "Exporter: background collection didn't finish within the expected time";
+
+pub const BITMASK_JOB_COUNT: u32 = 0x00000001;
+pub const BITMASK_JOB_CPUS: u32 = 0x00000002;
+pub const BITMASK_JOB_NODES: u32 = 0x00000004;
+pub const BITMASK_JOB_TASKS: u32 = 0x00000008;
+pub const BITMASK_PARTITIONS: u32 = 0x00000010;
+pub const BITMASK_NODES: u32 = 0x00000020;
+pub const BITMASK_GRES: u32 = 0x00000040;
+pub const BITMASK_PENDING_REASONS: u32 = 0x00000080;
+pub const BITMASK_PENDING_WAIT: u32 = 0x00000100;
+pub const BITMASK_JOB_RUNTIME: u32 = 0x00000200;
+pub const BITMASK_SDIAG: u32 = 0x00000400;
+pub const BITMASK_SSHARE: u32 = 0x00000800;
+pub const BITMASK_ACCOUNTING: u32 = 0x00001000;
+pub const BITMASK_PRIORITY: u32 = 0x00002000;
+pub const BITMASK_RESERVATIONS: u32 = 0x00004000;
+pub const BITMASK_LICENSES: u32 = 0x00008000;
+pub const BITMASK_NODE_REASONS: u32 = 0x00010000;
+pub const BITMASK_PARTITION_CONFIG: u32 = 0x00020000;
+pub const BITMASK_CONTROLLERS: u32 = 0x00040000;
+pub const BITMASK_JOBS: u32 = BITMASK_JOB_COUNT
+    | BITMASK_JOB_CPUS
+    | BITMASK_JOB_NODES
+    | BITMASK_JOB_TASKS
+    | BITMASK_PENDING_REASONS
+    | BITMASK_PENDING_WAIT
+    | BITMASK_JOB_RUNTIME;
+
+pub const COLLECTOR_ACCOUNTING: &str = "accounting";
+pub const COLLECTOR_CONTROLLERS: &str = "controllers";
+pub const COLLECTOR_GRES: &str = "gres";
+pub const COLLECTOR_JOBS: &str = "jobs";
+pub const COLLECTOR_LICENSES: &str = "licenses";
+pub const COLLECTOR_NODE_REASONS: &str = "node_reasons";
+pub const COLLECTOR_NODES: &str = "nodes";
+pub const COLLECTOR_PARTITION_CONFIG: &str = "partition_config";
+pub const COLLECTOR_PARTITIONS: &str = "partitions";
+pub const COLLECTOR_PRIORITY: &str = "priority";
+pub const COLLECTOR_RESERVATIONS: &str = "reservations";
+pub const COLLECTOR_SDIAG: &str = "sdiag";
+pub const COLLECTOR_SSHARE: &str = "sshare";
 
-pub const BITMASK_JOB_COUNT: u8 = 0x01;
-pub const BITMASK_JOB_CPUS: u8 = 0x02;
-pub const BITMASK_JOB_NODES: u8 = 0x04;
-pub const BITMASK_JOB_TASKS: u8 = 0x08;
-pub const BITMASK_PARTITIONS: u8 = 0x10;
+// Collectors in order of execution and the bitmask of the metrics they export
+pub const COLLECTORS: &[(&str, u32)] = &[
+    (COLLECTOR_CONTROLLERS, BITMASK_CONTROLLERS),
+    (COLLECTOR_PARTITIONS, BITMASK_PARTITIONS),
+    (COLLECTOR_PARTITION_CONFIG, BITMASK_PARTITION_CONFIG),
+    (COLLECTOR_NODES, BITMASK_NODES),
+    (COLLECTOR_NODE_REASONS, BITMASK_NODE_REASONS),
+    (COLLECTOR_GRES, BITMASK_GRES),
+    (COLLECTOR_SDIAG, BITMASK_SDIAG),
+    (COLLECTOR_SSHARE, BITMASK_SSHARE),
+    (COLLECTOR_ACCOUNTING, BITMASK_ACCOUNTING),
+    (COLLECTOR_PRIORITY, BITMASK_PRIORITY),
+    (COLLECTOR_RESERVATIONS, BITMASK_RESERVATIONS),
+    (COLLECTOR_LICENSES, BITMASK_LICENSES),
+    (COLLECTOR_JOBS, BITMASK_JOBS),
+];
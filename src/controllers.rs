@@ -0,0 +1,139 @@
+use crate::command;
+use crate::exporter;
+use crate::slurm;
+
+use log::{debug, warn};
+use std::error::Error;
+use std::time::Instant;
+
+pub struct ControllerData {
+    pub cluster: String,
+    pub host: String,
+    pub role: String,
+    pub up: bool,
+}
+
+pub fn update_controller_metrics(slurm_cluster: &str) -> Result<(), Box<dyn Error>> {
+    let mut controllers: Vec<ControllerData> = Vec::new();
+    // (<cluster>, <seconds>)
+    let mut durations: Vec<(String, f64)> = Vec::new();
+
+    for cluster in slurm::cluster_names(slurm_cluster)? {
+        let start = Instant::now();
+        // scontrol ping exits with non-zero exit code if a controller is down. A failing
+        // controller is not retried, the duration would include the backoff of the retries
+        let result = command::run_command_status_once(
+            "scontrol",
+            &[format!("--clusters={}", cluster), "ping".to_string()],
+        );
+        durations.push((cluster.clone(), start.elapsed().as_secs_f64()));
+
+        // the controllers of a cluster which can't be pinged at all are reported as a single
+        // controller without host and role, a failing cluster must not hide the state of
+        // the other clusters
+        let unreachable = ControllerData {
+            cluster: cluster.clone(),
+            host: String::new(),
+            role: String::new(),
+            up: false,
+        };
+        let (rc, stdout) = match result {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Can't ping controllers of cluster {}: {}", cluster, e);
+                controllers.push(unreachable);
+                continue;
+            }
+        };
+
+        let mut found = false;
+        for line in stdout.lines() {
+            debug!(
+                "controllers.rs:update_controller_metrics: Processing line: {}",
+                line
+            );
+            if let Some((role, host, up)) = split_ping_line(line) {
+                controllers.push(ControllerData {
+                    cluster: cluster.clone(),
+                    host,
+                    role,
+                    up,
+                });
+                found = true;
+            }
+        }
+        if !found {
+            warn!(
+                "Can't extract controller state from scontrol ping output for cluster {} (exit code {})",
+                cluster, rc
+            );
+            controllers.push(unreachable);
+        }
+    }
+
+    exporter::CONTROLLER_UP.reset();
+    exporter::CONTROLLER_PING_DURATION.reset();
+
+    for c in controllers.iter() {
+        debug!(
+            "controllers.rs:update_controller_metrics: Setting CONTROLLER_UP {} {} {} -> {}",
+            c.cluster, c.host, c.role, c.up
+        );
+        exporter::CONTROLLER_UP
+            .with_label_values(&[&c.cluster, &c.host, &c.role])
+            .set(c.up as i64);
+    }
+    for (cluster, elapsed) in durations.iter() {
+        debug!(
+            "controllers.rs:update_controller_metrics: Setting CONTROLLER_PING_DURATION {} -> {}",
+            cluster, elapsed
+        );
+        exporter::CONTROLLER_PING_DURATION
+            .with_label_values(&[cluster])
+            .set(*elapsed);
+    }
+    Ok(())
+}
+
+// Parse lines like
+//   Slurmctld(primary) at ctl01 is UP
+//   Slurmctld(backup1) at ctl02 is DOWN
+// into (<role>, <host>, <up>). Numbered backup controllers are reported as role backup
+fn split_ping_line(s: &str) -> Option<(String, String, bool)> {
+    let remain = s.trim().strip_prefix("Slurmctld(")?;
+    let (role, remain) = remain.split_once(')')?;
+    let remain = remain.trim().strip_prefix("at ")?;
+    let (host, state) = remain.split_once(" is ")?;
+
+    let role = if role.starts_with("backup") {
+        "backup"
+    } else {
+        role
+    };
+    Some((
+        role.to_string(),
+        host.trim().to_string(),
+        state.trim() == "UP",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_ping_line() {
+        assert_eq!(
+            split_ping_line("Slurmctld(primary) at ctl01 is UP"),
+            Some(("primary".to_string(), "ctl01".to_string(), true))
+        );
+        assert_eq!(
+            split_ping_line("Slurmctld(backup1) at ctl02 is DOWN"),
+            Some(("backup".to_string(), "ctl02".to_string(), false))
+        );
+        assert_eq!(
+            split_ping_line("slurm_load_ctl_conf error: Unable to contact slurm controller"),
+            None
+        );
+    }
+}
@@ -1,9 +1,30 @@
+use crate::config;
 use crate::constants;
+use crate::controllers;
+use crate::gres;
+use crate::licenses;
+use crate::partitions;
+use crate::reasons;
+use crate::reservations;
+use crate::sacct;
+use crate::sdiag;
 use crate::slurm;
+use crate::slurm::label_refs;
+use crate::sprio;
+use crate::sshare;
 
 use lazy_static::lazy_static;
 use log::{debug, error};
-use prometheus::{IntGaugeVec, Opts, Registry, TextEncoder};
+use prometheus::{
+    proto::MetricFamily, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::{task, time};
 
 lazy_static! {
     pub static ref REGISTRY: Registry = Registry::new();
@@ -12,7 +33,7 @@ lazy_static! {
             constants::METRIC_JOBS_NODES_NAME,
             constants::METRIC_JOBS_NODES_HELP
         ),
-        &["cluster", "partition", "state"],
+        &label_refs(&job_label_names()),
     )
     .unwrap();
     pub static ref JOBS_TASKS: IntGaugeVec = IntGaugeVec::new(
@@ -20,7 +41,7 @@ lazy_static! {
             constants::METRIC_JOBS_TASKS_NAME,
             constants::METRIC_JOBS_TASKS_HELP
         ),
-        &["cluster", "partition", "state"],
+        &label_refs(&job_label_names()),
     )
     .unwrap();
     pub static ref JOBS_CPUS: IntGaugeVec = IntGaugeVec::new(
@@ -28,7 +49,7 @@ lazy_static! {
             constants::METRIC_JOBS_CPUS_NAME,
             constants::METRIC_JOBS_CPUS_HELP
         ),
-        &["cluster", "partition", "state"],
+        &label_refs(&job_label_names()),
     )
     .unwrap();
     pub static ref PARTITIONS: IntGaugeVec = IntGaugeVec::new(
@@ -44,51 +65,1381 @@ lazy_static! {
             constants::METRIC_JOBS_COUNT_NAME,
             constants::METRIC_JOBS_COUNT_HELP,
         ),
+        &label_refs(&job_label_names()),
+    )
+    .unwrap();
+    // Serialises collection and encoding so a scrape never sees partially replaced series
+    static ref SCRAPE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+    pub static ref PARTITION_CPUS_ALLOCATED: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_PARTITION_CPUS_ALLOCATED_NAME,
+            constants::METRIC_PARTITION_CPUS_ALLOCATED_HELP
+        ),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref PARTITION_CPUS_IDLE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_PARTITION_CPUS_IDLE_NAME,
+            constants::METRIC_PARTITION_CPUS_IDLE_HELP
+        ),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref PARTITION_CPUS_OTHER: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_PARTITION_CPUS_OTHER_NAME,
+            constants::METRIC_PARTITION_CPUS_OTHER_HELP
+        ),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref PARTITION_CPUS_TOTAL: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_PARTITION_CPUS_TOTAL_NAME,
+            constants::METRIC_PARTITION_CPUS_TOTAL_HELP
+        ),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    // Label dimensions of job metrics between cluster and state, must be set
+    // before the job metrics are registered
+    static ref JOB_LABELS: RwLock<Vec<String>> = RwLock::new(
+        constants::DEFAULT_JOB_LABELS
+            .split(',')
+            .map(|v| v.to_string())
+            .collect()
+    );
+    pub static ref JOBS_PENDING_COUNT: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_JOBS_PENDING_COUNT_NAME,
+            constants::METRIC_JOBS_PENDING_COUNT_HELP
+        ),
+        &["cluster", "partition", "reason"],
+    )
+    .unwrap();
+    pub static ref JOBS_PENDING_CPUS: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_JOBS_PENDING_CPUS_NAME,
+            constants::METRIC_JOBS_PENDING_CPUS_HELP
+        ),
+        &["cluster", "partition", "reason"],
+    )
+    .unwrap();
+    pub static ref JOBS_PENDING_NODES: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_JOBS_PENDING_NODES_NAME,
+            constants::METRIC_JOBS_PENDING_NODES_HELP
+        ),
+        &["cluster", "partition", "reason"],
+    )
+    .unwrap();
+    pub static ref JOBS_PENDING_WAIT: HistogramVec = HistogramVec::new(
+        HistogramOpts::new(
+            constants::METRIC_JOBS_PENDING_WAIT_NAME,
+            constants::METRIC_JOBS_PENDING_WAIT_HELP
+        )
+        .buckets(constants::WAIT_TIME_BUCKETS.to_vec()),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref JOBS_PENDING_ELIGIBLE_WAIT: HistogramVec = HistogramVec::new(
+        HistogramOpts::new(
+            constants::METRIC_JOBS_PENDING_ELIGIBLE_WAIT_NAME,
+            constants::METRIC_JOBS_PENDING_ELIGIBLE_WAIT_HELP
+        )
+        .buckets(constants::WAIT_TIME_BUCKETS.to_vec()),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref JOBS_PENDING_OLDEST_ELIGIBLE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_JOBS_PENDING_OLDEST_ELIGIBLE_NAME,
+            constants::METRIC_JOBS_PENDING_OLDEST_ELIGIBLE_HELP
+        ),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref JOBS_RUNNING_ELAPSED: HistogramVec = HistogramVec::new(
+        HistogramOpts::new(
+            constants::METRIC_JOBS_RUNNING_ELAPSED_NAME,
+            constants::METRIC_JOBS_RUNNING_ELAPSED_HELP
+        )
+        .buckets(constants::RUNTIME_BUCKETS.to_vec()),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref JOBS_RUNNING_TIME_LIMIT_RATIO: HistogramVec = HistogramVec::new(
+        HistogramOpts::new(
+            constants::METRIC_JOBS_RUNNING_TIME_LIMIT_RATIO_NAME,
+            constants::METRIC_JOBS_RUNNING_TIME_LIMIT_RATIO_HELP
+        )
+        .buckets(constants::TIME_LIMIT_RATIO_BUCKETS.to_vec()),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref JOBS_RUNNING_NEAR_TIME_LIMIT: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_JOBS_RUNNING_NEAR_TIME_LIMIT_NAME,
+            constants::METRIC_JOBS_RUNNING_NEAR_TIME_LIMIT_HELP
+        ),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref JOBS_ARRAY_JOBS_PENDING: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_JOBS_ARRAY_JOBS_PENDING_NAME,
+            constants::METRIC_JOBS_ARRAY_JOBS_PENDING_HELP
+        ),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref JOBS_ARRAY_TASKS_PENDING: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_JOBS_ARRAY_TASKS_PENDING_NAME,
+            constants::METRIC_JOBS_ARRAY_TASKS_PENDING_HELP
+        ),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    // Every collector registers its metrics in a registry of its own, see constants::COLLECTORS
+    static ref COLLECTOR_REGISTRIES: HashMap<&'static str, Registry> = constants::COLLECTORS
+        .iter()
+        .map(|(name, _)| (*name, Registry::new()))
+        .collect();
+    // UNIX timestamp and encoded metrics of the last collection
+    static ref SNAPSHOT: RwLock<(i64, String)> = RwLock::new((0, String::new()));
+    // Exporter metrics describing the served snapshot, updated on every scrape
+    static ref STATUS_REGISTRY: Registry = Registry::new();
+    // Number of finished collections
+    static ref COLLECTIONS: AtomicU64 = AtomicU64::new(0);
+    // UNIX timestamp of the last successful run and result of the last run of every collector
+    static ref COLLECTOR_STATUS: Mutex<HashMap<&'static str, (i64, bool)>> = Mutex::new(HashMap::new());
+}
+
+lazy_static! {
+    pub static ref NODE_STATE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_NODE_STATE_NAME,
+            constants::METRIC_NODE_STATE_HELP
+        ),
+        &["cluster", "node", "partition", "state"],
+    )
+    .unwrap();
+    pub static ref NODE_CPUS_ALLOCATED: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_NODE_CPUS_ALLOCATED_NAME,
+            constants::METRIC_NODE_CPUS_ALLOCATED_HELP
+        ),
+        &["cluster", "node", "partition"],
+    )
+    .unwrap();
+    pub static ref NODE_CPUS_IDLE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_NODE_CPUS_IDLE_NAME,
+            constants::METRIC_NODE_CPUS_IDLE_HELP
+        ),
+        &["cluster", "node", "partition"],
+    )
+    .unwrap();
+    pub static ref NODE_CPUS_OTHER: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_NODE_CPUS_OTHER_NAME,
+            constants::METRIC_NODE_CPUS_OTHER_HELP
+        ),
+        &["cluster", "node", "partition"],
+    )
+    .unwrap();
+    pub static ref NODE_CPUS_TOTAL: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_NODE_CPUS_TOTAL_NAME,
+            constants::METRIC_NODE_CPUS_TOTAL_HELP
+        ),
+        &["cluster", "node", "partition"],
+    )
+    .unwrap();
+    pub static ref NODE_MEMORY: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_NODE_MEMORY_NAME,
+            constants::METRIC_NODE_MEMORY_HELP
+        ),
+        &["cluster", "node", "partition"],
+    )
+    .unwrap();
+    pub static ref NODE_MEMORY_ALLOCATED: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_NODE_MEMORY_ALLOCATED_NAME,
+            constants::METRIC_NODE_MEMORY_ALLOCATED_HELP
+        ),
+        &["cluster", "node", "partition"],
+    )
+    .unwrap();
+    pub static ref NODE_MEMORY_FREE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_NODE_MEMORY_FREE_NAME,
+            constants::METRIC_NODE_MEMORY_FREE_HELP
+        ),
+        &["cluster", "node", "partition"],
+    )
+    .unwrap();
+    pub static ref NODE_CPU_LOAD: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_NODE_CPU_LOAD_NAME,
+            constants::METRIC_NODE_CPU_LOAD_HELP
+        ),
+        &["cluster", "node", "partition"],
+    )
+    .unwrap();
+}
+
+lazy_static! {
+    pub static ref PARTITION_GRES_CONFIGURED: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_PARTITION_GRES_CONFIGURED_NAME,
+            constants::METRIC_PARTITION_GRES_CONFIGURED_HELP
+        ),
+        &["cluster", "partition", "gres"],
+    )
+    .unwrap();
+    pub static ref PARTITION_GRES_ALLOCATED: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_PARTITION_GRES_ALLOCATED_NAME,
+            constants::METRIC_PARTITION_GRES_ALLOCATED_HELP
+        ),
+        &["cluster", "partition", "gres"],
+    )
+    .unwrap();
+    pub static ref PARTITION_GRES_IDLE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_PARTITION_GRES_IDLE_NAME,
+            constants::METRIC_PARTITION_GRES_IDLE_HELP
+        ),
+        &["cluster", "partition", "gres"],
+    )
+    .unwrap();
+    pub static ref JOBS_PENDING_GRES: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_JOBS_PENDING_GRES_NAME,
+            constants::METRIC_JOBS_PENDING_GRES_HELP
+        ),
+        &["cluster", "partition", "gres"],
+    )
+    .unwrap();
+}
+
+lazy_static! {
+    pub static ref SCHEDULER_SERVER_THREADS: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_SCHEDULER_SERVER_THREADS_NAME,
+            constants::METRIC_SCHEDULER_SERVER_THREADS_HELP
+        ),
+        &["cluster"],
+    )
+    .unwrap();
+    pub static ref SCHEDULER_AGENT_QUEUE_SIZE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_SCHEDULER_AGENT_QUEUE_SIZE_NAME,
+            constants::METRIC_SCHEDULER_AGENT_QUEUE_SIZE_HELP
+        ),
+        &["cluster"],
+    )
+    .unwrap();
+    pub static ref SCHEDULER_AGENT_COUNT: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_SCHEDULER_AGENT_COUNT_NAME,
+            constants::METRIC_SCHEDULER_AGENT_COUNT_HELP
+        ),
+        &["cluster"],
+    )
+    .unwrap();
+    pub static ref SCHEDULER_AGENT_THREADS: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_SCHEDULER_AGENT_THREADS_NAME,
+            constants::METRIC_SCHEDULER_AGENT_THREADS_HELP
+        ),
+        &["cluster"],
+    )
+    .unwrap();
+    pub static ref SCHEDULER_DBD_AGENT_QUEUE_SIZE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_SCHEDULER_DBD_AGENT_QUEUE_SIZE_NAME,
+            constants::METRIC_SCHEDULER_DBD_AGENT_QUEUE_SIZE_HELP
+        ),
+        &["cluster"],
+    )
+    .unwrap();
+    pub static ref SCHEDULER_CYCLE_LAST: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_SCHEDULER_CYCLE_LAST_NAME,
+            constants::METRIC_SCHEDULER_CYCLE_LAST_HELP
+        ),
+        &["cluster", "scheduler"],
+    )
+    .unwrap();
+    pub static ref SCHEDULER_CYCLE_MAX: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_SCHEDULER_CYCLE_MAX_NAME,
+            constants::METRIC_SCHEDULER_CYCLE_MAX_HELP
+        ),
+        &["cluster", "scheduler"],
+    )
+    .unwrap();
+    pub static ref SCHEDULER_CYCLE_MEAN: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_SCHEDULER_CYCLE_MEAN_NAME,
+            constants::METRIC_SCHEDULER_CYCLE_MEAN_HELP
+        ),
+        &["cluster", "scheduler"],
+    )
+    .unwrap();
+    pub static ref SCHEDULER_DEPTH_MEAN: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_SCHEDULER_DEPTH_MEAN_NAME,
+            constants::METRIC_SCHEDULER_DEPTH_MEAN_HELP
+        ),
+        &["cluster", "scheduler"],
+    )
+    .unwrap();
+    pub static ref SCHEDULER_BACKFILL_DEPTH_LAST: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_SCHEDULER_BACKFILL_DEPTH_LAST_NAME,
+            constants::METRIC_SCHEDULER_BACKFILL_DEPTH_LAST_HELP
+        ),
+        &["cluster"],
+    )
+    .unwrap();
+    pub static ref SCHEDULER_BACKFILLED_JOBS: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_SCHEDULER_BACKFILLED_JOBS_NAME,
+            constants::METRIC_SCHEDULER_BACKFILLED_JOBS_HELP
+        ),
+        &["cluster"],
+    )
+    .unwrap();
+    pub static ref SCHEDULER_BACKFILLED_JOBS_SINCE_CYCLE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_SCHEDULER_BACKFILLED_JOBS_SINCE_CYCLE_NAME,
+            constants::METRIC_SCHEDULER_BACKFILLED_JOBS_SINCE_CYCLE_HELP
+        ),
+        &["cluster"],
+    )
+    .unwrap();
+    pub static ref RPC_TYPE_COUNT: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_RPC_TYPE_COUNT_NAME,
+            constants::METRIC_RPC_TYPE_COUNT_HELP
+        ),
+        &["cluster", "type"],
+    )
+    .unwrap();
+    pub static ref RPC_TYPE_AVG_TIME: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_RPC_TYPE_AVG_TIME_NAME,
+            constants::METRIC_RPC_TYPE_AVG_TIME_HELP
+        ),
+        &["cluster", "type"],
+    )
+    .unwrap();
+    pub static ref RPC_TYPE_TOTAL_TIME: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_RPC_TYPE_TOTAL_TIME_NAME,
+            constants::METRIC_RPC_TYPE_TOTAL_TIME_HELP
+        ),
+        &["cluster", "type"],
+    )
+    .unwrap();
+    pub static ref RPC_USER_COUNT: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_RPC_USER_COUNT_NAME,
+            constants::METRIC_RPC_USER_COUNT_HELP
+        ),
+        &["cluster", "user"],
+    )
+    .unwrap();
+    pub static ref RPC_USER_AVG_TIME: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_RPC_USER_AVG_TIME_NAME,
+            constants::METRIC_RPC_USER_AVG_TIME_HELP
+        ),
+        &["cluster", "user"],
+    )
+    .unwrap();
+    pub static ref RPC_USER_TOTAL_TIME: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_RPC_USER_TOTAL_TIME_NAME,
+            constants::METRIC_RPC_USER_TOTAL_TIME_HELP
+        ),
+        &["cluster", "user"],
+    )
+    .unwrap();
+}
+
+lazy_static! {
+    pub static ref SHARE_RAW_SHARES: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_SHARE_RAW_SHARES_NAME,
+            constants::METRIC_SHARE_RAW_SHARES_HELP
+        ),
+        &["cluster", "account", "user"],
+    )
+    .unwrap();
+    pub static ref SHARE_NORM_SHARES: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_SHARE_NORM_SHARES_NAME,
+            constants::METRIC_SHARE_NORM_SHARES_HELP
+        ),
+        &["cluster", "account", "user"],
+    )
+    .unwrap();
+    pub static ref SHARE_RAW_USAGE: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_SHARE_RAW_USAGE_NAME,
+            constants::METRIC_SHARE_RAW_USAGE_HELP
+        ),
+        &["cluster", "account", "user"],
+    )
+    .unwrap();
+    pub static ref SHARE_EFFECTIVE_USAGE: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_SHARE_EFFECTIVE_USAGE_NAME,
+            constants::METRIC_SHARE_EFFECTIVE_USAGE_HELP
+        ),
+        &["cluster", "account", "user"],
+    )
+    .unwrap();
+    pub static ref SHARE_FAIRSHARE: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_SHARE_FAIRSHARE_NAME,
+            constants::METRIC_SHARE_FAIRSHARE_HELP
+        ),
+        &["cluster", "account", "user"],
+    )
+    .unwrap();
+}
+
+lazy_static! {
+    pub static ref JOBS_FINISHED: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            constants::METRIC_JOBS_FINISHED_NAME,
+            constants::METRIC_JOBS_FINISHED_HELP
+        ),
+        &["cluster", "partition", "state", "exit_class"],
+    )
+    .unwrap();
+    pub static ref JOBS_FINISHED_CPU_SECONDS: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            constants::METRIC_JOBS_FINISHED_CPU_SECONDS_NAME,
+            constants::METRIC_JOBS_FINISHED_CPU_SECONDS_HELP
+        ),
         &["cluster", "partition", "state"],
     )
     .unwrap();
 }
 
-pub fn register(bitmask: u8) {
+lazy_static! {
+    pub static ref JOBS_PRIORITY: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_JOBS_PRIORITY_NAME,
+            constants::METRIC_JOBS_PRIORITY_HELP
+        ),
+        &["cluster", "partition", "component", "quantile"],
+    )
+    .unwrap();
+}
+
+lazy_static! {
+    pub static ref RESERVATION_NODES: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_RESERVATION_NODES_NAME,
+            constants::METRIC_RESERVATION_NODES_HELP
+        ),
+        &["cluster", "reservation"],
+    )
+    .unwrap();
+    pub static ref RESERVATION_CORES: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_RESERVATION_CORES_NAME,
+            constants::METRIC_RESERVATION_CORES_HELP
+        ),
+        &["cluster", "reservation"],
+    )
+    .unwrap();
+    pub static ref RESERVATION_ACTIVE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_RESERVATION_ACTIVE_NAME,
+            constants::METRIC_RESERVATION_ACTIVE_HELP
+        ),
+        &["cluster", "reservation"],
+    )
+    .unwrap();
+    pub static ref RESERVATION_SECONDS_UNTIL_START: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_RESERVATION_SECONDS_UNTIL_START_NAME,
+            constants::METRIC_RESERVATION_SECONDS_UNTIL_START_HELP
+        ),
+        &["cluster", "reservation"],
+    )
+    .unwrap();
+    pub static ref RESERVATION_SECONDS_UNTIL_END: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_RESERVATION_SECONDS_UNTIL_END_NAME,
+            constants::METRIC_RESERVATION_SECONDS_UNTIL_END_HELP
+        ),
+        &["cluster", "reservation"],
+    )
+    .unwrap();
+    pub static ref RESERVATION_FLAGS: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_RESERVATION_FLAGS_NAME,
+            constants::METRIC_RESERVATION_FLAGS_HELP
+        ),
+        &["cluster", "reservation", "flag"],
+    )
+    .unwrap();
+    pub static ref MAINTENANCE_ACTIVE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_MAINTENANCE_ACTIVE_NAME,
+            constants::METRIC_MAINTENANCE_ACTIVE_HELP
+        ),
+        &["cluster"],
+    )
+    .unwrap();
+}
+
+lazy_static! {
+    pub static ref LICENSE_TOTAL: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_LICENSE_TOTAL_NAME,
+            constants::METRIC_LICENSE_TOTAL_HELP
+        ),
+        &["cluster", "license"],
+    )
+    .unwrap();
+    pub static ref LICENSE_USED: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_LICENSE_USED_NAME,
+            constants::METRIC_LICENSE_USED_HELP
+        ),
+        &["cluster", "license"],
+    )
+    .unwrap();
+    pub static ref LICENSE_FREE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_LICENSE_FREE_NAME,
+            constants::METRIC_LICENSE_FREE_HELP
+        ),
+        &["cluster", "license"],
+    )
+    .unwrap();
+    pub static ref LICENSE_RESERVED: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_LICENSE_RESERVED_NAME,
+            constants::METRIC_LICENSE_RESERVED_HELP
+        ),
+        &["cluster", "license"],
+    )
+    .unwrap();
+    pub static ref LICENSE_PENDING_JOBS: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_LICENSE_PENDING_JOBS_NAME,
+            constants::METRIC_LICENSE_PENDING_JOBS_HELP
+        ),
+        &["cluster", "license"],
+    )
+    .unwrap();
+    pub static ref LICENSE_REMOTE_COUNT: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_LICENSE_REMOTE_COUNT_NAME,
+            constants::METRIC_LICENSE_REMOTE_COUNT_HELP
+        ),
+        &["license", "server"],
+    )
+    .unwrap();
+    pub static ref LICENSE_REMOTE_ALLOCATED: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_LICENSE_REMOTE_ALLOCATED_NAME,
+            constants::METRIC_LICENSE_REMOTE_ALLOCATED_HELP
+        ),
+        &["license", "server"],
+    )
+    .unwrap();
+}
+
+lazy_static! {
+    pub static ref NODE_REASON: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_NODE_REASON_NAME,
+            constants::METRIC_NODE_REASON_HELP
+        ),
+        &["cluster", "node", "state", "reason", "user"],
+    )
+    .unwrap();
+    pub static ref NODE_REASON_SECONDS: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_NODE_REASON_SECONDS_NAME,
+            constants::METRIC_NODE_REASON_SECONDS_HELP
+        ),
+        &["cluster", "node"],
+    )
+    .unwrap();
+}
+
+lazy_static! {
+    pub static ref PARTITION_STATE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_PARTITION_STATE_NAME,
+            constants::METRIC_PARTITION_STATE_HELP
+        ),
+        &["cluster", "partition", "state"],
+    )
+    .unwrap();
+    pub static ref PARTITION_DEFAULT: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_PARTITION_DEFAULT_NAME,
+            constants::METRIC_PARTITION_DEFAULT_HELP
+        ),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref PARTITION_NODES_CONFIGURED: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_PARTITION_NODES_CONFIGURED_NAME,
+            constants::METRIC_PARTITION_NODES_CONFIGURED_HELP
+        ),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref PARTITION_CPUS_CONFIGURED: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_PARTITION_CPUS_CONFIGURED_NAME,
+            constants::METRIC_PARTITION_CPUS_CONFIGURED_HELP
+        ),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref PARTITION_MAX_TIME: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_PARTITION_MAX_TIME_NAME,
+            constants::METRIC_PARTITION_MAX_TIME_HELP
+        ),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref PARTITION_DEFAULT_TIME: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_PARTITION_DEFAULT_TIME_NAME,
+            constants::METRIC_PARTITION_DEFAULT_TIME_HELP
+        ),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref PARTITION_MAX_NODES: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_PARTITION_MAX_NODES_NAME,
+            constants::METRIC_PARTITION_MAX_NODES_HELP
+        ),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref PARTITION_PRIORITY_TIER: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_PARTITION_PRIORITY_TIER_NAME,
+            constants::METRIC_PARTITION_PRIORITY_TIER_HELP
+        ),
+        &["cluster", "partition"],
+    )
+    .unwrap();
+    pub static ref PARTITION_PREEMPT_MODE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_PARTITION_PREEMPT_MODE_NAME,
+            constants::METRIC_PARTITION_PREEMPT_MODE_HELP
+        ),
+        &["cluster", "partition", "mode"],
+    )
+    .unwrap();
+}
+
+lazy_static! {
+    pub static ref CONTROLLER_UP: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_CONTROLLER_UP_NAME,
+            constants::METRIC_CONTROLLER_UP_HELP
+        ),
+        &["cluster", "host", "role"],
+    )
+    .unwrap();
+    pub static ref CONTROLLER_PING_DURATION: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_CONTROLLER_PING_DURATION_NAME,
+            constants::METRIC_CONTROLLER_PING_DURATION_HELP
+        ),
+        &["cluster"],
+    )
+    .unwrap();
+}
+
+lazy_static! {
+    pub static ref COLLECTOR_SUCCESS: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_COLLECTOR_SUCCESS_NAME,
+            constants::METRIC_COLLECTOR_SUCCESS_HELP
+        ),
+        &["collector"],
+    )
+    .unwrap();
+    pub static ref COLLECTOR_DURATION: GaugeVec = GaugeVec::new(
+        Opts::new(
+            constants::METRIC_COLLECTOR_DURATION_NAME,
+            constants::METRIC_COLLECTOR_DURATION_HELP
+        ),
+        &["collector"],
+    )
+    .unwrap();
+    pub static ref LAST_COLLECTION: IntGauge = IntGauge::with_opts(Opts::new(
+        constants::METRIC_LAST_COLLECTION_NAME,
+        constants::METRIC_LAST_COLLECTION_HELP
+    ))
+    .unwrap();
+    pub static ref COLLECTION_STALE: IntGauge = IntGauge::with_opts(Opts::new(
+        constants::METRIC_COLLECTION_STALE_NAME,
+        constants::METRIC_COLLECTION_STALE_HELP
+    ))
+    .unwrap();
+    pub static ref COLLECTOR_LAST_UPDATE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            constants::METRIC_COLLECTOR_LAST_UPDATE_NAME,
+            constants::METRIC_COLLECTOR_LAST_UPDATE_HELP
+        ),
+        &["collector"],
+    )
+    .unwrap();
+}
+
+pub fn set_job_labels(labels: Vec<String>) {
+    match JOB_LABELS.write() {
+        Ok(mut v) => *v = labels,
+        Err(e) => *e.into_inner() = labels,
+    };
+}
+
+pub fn job_labels() -> Vec<String> {
+    match JOB_LABELS.read() {
+        Ok(v) => v.clone(),
+        Err(e) => e.into_inner().clone(),
+    }
+}
+
+fn job_label_names() -> Vec<String> {
+    let mut result = vec!["cluster".to_string()];
+    result.extend(job_labels());
+    result.push("state".to_string());
+    result
+}
+
+pub fn register(bitmask: u32) {
+    debug!("Registering registry LAST_COLLECTION");
+    STATUS_REGISTRY
+        .register(Box::new(LAST_COLLECTION.clone()))
+        .unwrap();
+    debug!("Registering registry COLLECTION_STALE");
+    STATUS_REGISTRY
+        .register(Box::new(COLLECTION_STALE.clone()))
+        .unwrap();
+    debug!("Registering registry COLLECTOR_SUCCESS");
+    REGISTRY
+        .register(Box::new(COLLECTOR_SUCCESS.clone()))
+        .unwrap();
+    debug!("Registering registry COLLECTOR_DURATION");
+    REGISTRY
+        .register(Box::new(COLLECTOR_DURATION.clone()))
+        .unwrap();
+    debug!("Registering registry COLLECTOR_LAST_UPDATE");
+    REGISTRY
+        .register(Box::new(COLLECTOR_LAST_UPDATE.clone()))
+        .unwrap();
     if bitmask & constants::BITMASK_JOB_NODES == constants::BITMASK_JOB_NODES {
+        let registry = collector_registry(constants::BITMASK_JOB_NODES);
         debug!("Registering registry JOBS_NODES");
-        REGISTRY.register(Box::new(JOBS_NODES.clone())).unwrap();
+        registry.register(Box::new(JOBS_NODES.clone())).unwrap();
     }
     if bitmask & constants::BITMASK_JOB_TASKS == constants::BITMASK_JOB_TASKS {
+        let registry = collector_registry(constants::BITMASK_JOB_TASKS);
         debug!("Registering registry JOBS_TASKS");
-        REGISTRY.register(Box::new(JOBS_TASKS.clone())).unwrap();
+        registry.register(Box::new(JOBS_TASKS.clone())).unwrap();
     }
     if bitmask & constants::BITMASK_JOB_CPUS == constants::BITMASK_JOB_CPUS {
+        let registry = collector_registry(constants::BITMASK_JOB_CPUS);
         debug!("Registering registry JOBS_CPUS");
-        REGISTRY.register(Box::new(JOBS_CPUS.clone())).unwrap();
+        registry.register(Box::new(JOBS_CPUS.clone())).unwrap();
     }
     if bitmask & constants::BITMASK_JOB_COUNT == constants::BITMASK_JOB_COUNT {
+        let registry = collector_registry(constants::BITMASK_JOB_COUNT);
         debug!("Registering registry JOBS_COUNT");
-        REGISTRY.register(Box::new(JOBS_COUNT.clone())).unwrap();
+        registry.register(Box::new(JOBS_COUNT.clone())).unwrap();
+        debug!("Registering registry JOBS_ARRAY_JOBS_PENDING");
+        registry
+            .register(Box::new(JOBS_ARRAY_JOBS_PENDING.clone()))
+            .unwrap();
+        debug!("Registering registry JOBS_ARRAY_TASKS_PENDING");
+        registry
+            .register(Box::new(JOBS_ARRAY_TASKS_PENDING.clone()))
+            .unwrap();
     }
     if bitmask & constants::BITMASK_PARTITIONS == constants::BITMASK_PARTITIONS {
+        let registry = collector_registry(constants::BITMASK_PARTITIONS);
         debug!("Registering registry PARTITIONS");
-        REGISTRY.register(Box::new(PARTITIONS.clone())).unwrap();
+        registry.register(Box::new(PARTITIONS.clone())).unwrap();
+        debug!("Registering registry PARTITION_CPUS_ALLOCATED");
+        registry
+            .register(Box::new(PARTITION_CPUS_ALLOCATED.clone()))
+            .unwrap();
+        debug!("Registering registry PARTITION_CPUS_IDLE");
+        registry
+            .register(Box::new(PARTITION_CPUS_IDLE.clone()))
+            .unwrap();
+        debug!("Registering registry PARTITION_CPUS_OTHER");
+        registry
+            .register(Box::new(PARTITION_CPUS_OTHER.clone()))
+            .unwrap();
+        debug!("Registering registry PARTITION_CPUS_TOTAL");
+        registry
+            .register(Box::new(PARTITION_CPUS_TOTAL.clone()))
+            .unwrap();
+    }
+    if bitmask & constants::BITMASK_NODES == constants::BITMASK_NODES {
+        let registry = collector_registry(constants::BITMASK_NODES);
+        debug!("Registering registry NODE_STATE");
+        registry.register(Box::new(NODE_STATE.clone())).unwrap();
+        debug!("Registering registry NODE_CPUS_ALLOCATED");
+        registry
+            .register(Box::new(NODE_CPUS_ALLOCATED.clone()))
+            .unwrap();
+        debug!("Registering registry NODE_CPUS_IDLE");
+        registry.register(Box::new(NODE_CPUS_IDLE.clone())).unwrap();
+        debug!("Registering registry NODE_CPUS_OTHER");
+        registry
+            .register(Box::new(NODE_CPUS_OTHER.clone()))
+            .unwrap();
+        debug!("Registering registry NODE_CPUS_TOTAL");
+        registry
+            .register(Box::new(NODE_CPUS_TOTAL.clone()))
+            .unwrap();
+        debug!("Registering registry NODE_MEMORY");
+        registry.register(Box::new(NODE_MEMORY.clone())).unwrap();
+        debug!("Registering registry NODE_MEMORY_ALLOCATED");
+        registry
+            .register(Box::new(NODE_MEMORY_ALLOCATED.clone()))
+            .unwrap();
+        debug!("Registering registry NODE_MEMORY_FREE");
+        registry
+            .register(Box::new(NODE_MEMORY_FREE.clone()))
+            .unwrap();
+        debug!("Registering registry NODE_CPU_LOAD");
+        registry.register(Box::new(NODE_CPU_LOAD.clone())).unwrap();
+    }
+    if bitmask & constants::BITMASK_GRES == constants::BITMASK_GRES {
+        let registry = collector_registry(constants::BITMASK_GRES);
+        debug!("Registering registry PARTITION_GRES_CONFIGURED");
+        registry
+            .register(Box::new(PARTITION_GRES_CONFIGURED.clone()))
+            .unwrap();
+        debug!("Registering registry PARTITION_GRES_ALLOCATED");
+        registry
+            .register(Box::new(PARTITION_GRES_ALLOCATED.clone()))
+            .unwrap();
+        debug!("Registering registry PARTITION_GRES_IDLE");
+        registry
+            .register(Box::new(PARTITION_GRES_IDLE.clone()))
+            .unwrap();
+        debug!("Registering registry JOBS_PENDING_GRES");
+        registry
+            .register(Box::new(JOBS_PENDING_GRES.clone()))
+            .unwrap();
+    }
+    if bitmask & constants::BITMASK_PENDING_REASONS == constants::BITMASK_PENDING_REASONS {
+        let registry = collector_registry(constants::BITMASK_PENDING_REASONS);
+        debug!("Registering registry JOBS_PENDING_COUNT");
+        registry
+            .register(Box::new(JOBS_PENDING_COUNT.clone()))
+            .unwrap();
+        debug!("Registering registry JOBS_PENDING_CPUS");
+        registry
+            .register(Box::new(JOBS_PENDING_CPUS.clone()))
+            .unwrap();
+        debug!("Registering registry JOBS_PENDING_NODES");
+        registry
+            .register(Box::new(JOBS_PENDING_NODES.clone()))
+            .unwrap();
+    }
+    if bitmask & constants::BITMASK_PENDING_WAIT == constants::BITMASK_PENDING_WAIT {
+        let registry = collector_registry(constants::BITMASK_PENDING_WAIT);
+        debug!("Registering registry JOBS_PENDING_WAIT");
+        registry
+            .register(Box::new(JOBS_PENDING_WAIT.clone()))
+            .unwrap();
+        debug!("Registering registry JOBS_PENDING_ELIGIBLE_WAIT");
+        registry
+            .register(Box::new(JOBS_PENDING_ELIGIBLE_WAIT.clone()))
+            .unwrap();
+        debug!("Registering registry JOBS_PENDING_OLDEST_ELIGIBLE");
+        registry
+            .register(Box::new(JOBS_PENDING_OLDEST_ELIGIBLE.clone()))
+            .unwrap();
+    }
+    if bitmask & constants::BITMASK_JOB_RUNTIME == constants::BITMASK_JOB_RUNTIME {
+        let registry = collector_registry(constants::BITMASK_JOB_RUNTIME);
+        debug!("Registering registry JOBS_RUNNING_ELAPSED");
+        registry
+            .register(Box::new(JOBS_RUNNING_ELAPSED.clone()))
+            .unwrap();
+        debug!("Registering registry JOBS_RUNNING_TIME_LIMIT_RATIO");
+        registry
+            .register(Box::new(JOBS_RUNNING_TIME_LIMIT_RATIO.clone()))
+            .unwrap();
+        debug!("Registering registry JOBS_RUNNING_NEAR_TIME_LIMIT");
+        registry
+            .register(Box::new(JOBS_RUNNING_NEAR_TIME_LIMIT.clone()))
+            .unwrap();
+    }
+    if bitmask & constants::BITMASK_SDIAG == constants::BITMASK_SDIAG {
+        let registry = collector_registry(constants::BITMASK_SDIAG);
+        debug!("Registering registry SCHEDULER_SERVER_THREADS");
+        registry
+            .register(Box::new(SCHEDULER_SERVER_THREADS.clone()))
+            .unwrap();
+        debug!("Registering registry SCHEDULER_AGENT_QUEUE_SIZE");
+        registry
+            .register(Box::new(SCHEDULER_AGENT_QUEUE_SIZE.clone()))
+            .unwrap();
+        debug!("Registering registry SCHEDULER_AGENT_COUNT");
+        registry
+            .register(Box::new(SCHEDULER_AGENT_COUNT.clone()))
+            .unwrap();
+        debug!("Registering registry SCHEDULER_AGENT_THREADS");
+        registry
+            .register(Box::new(SCHEDULER_AGENT_THREADS.clone()))
+            .unwrap();
+        debug!("Registering registry SCHEDULER_DBD_AGENT_QUEUE_SIZE");
+        registry
+            .register(Box::new(SCHEDULER_DBD_AGENT_QUEUE_SIZE.clone()))
+            .unwrap();
+        debug!("Registering registry SCHEDULER_CYCLE_LAST");
+        registry
+            .register(Box::new(SCHEDULER_CYCLE_LAST.clone()))
+            .unwrap();
+        debug!("Registering registry SCHEDULER_CYCLE_MAX");
+        registry
+            .register(Box::new(SCHEDULER_CYCLE_MAX.clone()))
+            .unwrap();
+        debug!("Registering registry SCHEDULER_CYCLE_MEAN");
+        registry
+            .register(Box::new(SCHEDULER_CYCLE_MEAN.clone()))
+            .unwrap();
+        debug!("Registering registry SCHEDULER_DEPTH_MEAN");
+        registry
+            .register(Box::new(SCHEDULER_DEPTH_MEAN.clone()))
+            .unwrap();
+        debug!("Registering registry SCHEDULER_BACKFILL_DEPTH_LAST");
+        registry
+            .register(Box::new(SCHEDULER_BACKFILL_DEPTH_LAST.clone()))
+            .unwrap();
+        debug!("Registering registry SCHEDULER_BACKFILLED_JOBS");
+        registry
+            .register(Box::new(SCHEDULER_BACKFILLED_JOBS.clone()))
+            .unwrap();
+        debug!("Registering registry SCHEDULER_BACKFILLED_JOBS_SINCE_CYCLE");
+        registry
+            .register(Box::new(SCHEDULER_BACKFILLED_JOBS_SINCE_CYCLE.clone()))
+            .unwrap();
+        debug!("Registering registry RPC_TYPE_COUNT");
+        registry.register(Box::new(RPC_TYPE_COUNT.clone())).unwrap();
+        debug!("Registering registry RPC_TYPE_AVG_TIME");
+        registry
+            .register(Box::new(RPC_TYPE_AVG_TIME.clone()))
+            .unwrap();
+        debug!("Registering registry RPC_TYPE_TOTAL_TIME");
+        registry
+            .register(Box::new(RPC_TYPE_TOTAL_TIME.clone()))
+            .unwrap();
+        debug!("Registering registry RPC_USER_COUNT");
+        registry.register(Box::new(RPC_USER_COUNT.clone())).unwrap();
+        debug!("Registering registry RPC_USER_AVG_TIME");
+        registry
+            .register(Box::new(RPC_USER_AVG_TIME.clone()))
+            .unwrap();
+        debug!("Registering registry RPC_USER_TOTAL_TIME");
+        registry
+            .register(Box::new(RPC_USER_TOTAL_TIME.clone()))
+            .unwrap();
+    }
+    if bitmask & constants::BITMASK_SSHARE == constants::BITMASK_SSHARE {
+        let registry = collector_registry(constants::BITMASK_SSHARE);
+        debug!("Registering registry SHARE_RAW_SHARES");
+        registry
+            .register(Box::new(SHARE_RAW_SHARES.clone()))
+            .unwrap();
+        debug!("Registering registry SHARE_NORM_SHARES");
+        registry
+            .register(Box::new(SHARE_NORM_SHARES.clone()))
+            .unwrap();
+        debug!("Registering registry SHARE_RAW_USAGE");
+        registry
+            .register(Box::new(SHARE_RAW_USAGE.clone()))
+            .unwrap();
+        debug!("Registering registry SHARE_EFFECTIVE_USAGE");
+        registry
+            .register(Box::new(SHARE_EFFECTIVE_USAGE.clone()))
+            .unwrap();
+        debug!("Registering registry SHARE_FAIRSHARE");
+        registry
+            .register(Box::new(SHARE_FAIRSHARE.clone()))
+            .unwrap();
+    }
+    if bitmask & constants::BITMASK_ACCOUNTING == constants::BITMASK_ACCOUNTING {
+        let registry = collector_registry(constants::BITMASK_ACCOUNTING);
+        debug!("Registering registry JOBS_FINISHED");
+        registry.register(Box::new(JOBS_FINISHED.clone())).unwrap();
+        debug!("Registering registry JOBS_FINISHED_CPU_SECONDS");
+        registry
+            .register(Box::new(JOBS_FINISHED_CPU_SECONDS.clone()))
+            .unwrap();
+    }
+    if bitmask & constants::BITMASK_PRIORITY == constants::BITMASK_PRIORITY {
+        let registry = collector_registry(constants::BITMASK_PRIORITY);
+        debug!("Registering registry JOBS_PRIORITY");
+        registry.register(Box::new(JOBS_PRIORITY.clone())).unwrap();
+    }
+    if bitmask & constants::BITMASK_RESERVATIONS == constants::BITMASK_RESERVATIONS {
+        let registry = collector_registry(constants::BITMASK_RESERVATIONS);
+        debug!("Registering registry RESERVATION_NODES");
+        registry
+            .register(Box::new(RESERVATION_NODES.clone()))
+            .unwrap();
+        debug!("Registering registry RESERVATION_CORES");
+        registry
+            .register(Box::new(RESERVATION_CORES.clone()))
+            .unwrap();
+        debug!("Registering registry RESERVATION_ACTIVE");
+        registry
+            .register(Box::new(RESERVATION_ACTIVE.clone()))
+            .unwrap();
+        debug!("Registering registry RESERVATION_SECONDS_UNTIL_START");
+        registry
+            .register(Box::new(RESERVATION_SECONDS_UNTIL_START.clone()))
+            .unwrap();
+        debug!("Registering registry RESERVATION_SECONDS_UNTIL_END");
+        registry
+            .register(Box::new(RESERVATION_SECONDS_UNTIL_END.clone()))
+            .unwrap();
+        debug!("Registering registry RESERVATION_FLAGS");
+        registry
+            .register(Box::new(RESERVATION_FLAGS.clone()))
+            .unwrap();
+        debug!("Registering registry MAINTENANCE_ACTIVE");
+        registry
+            .register(Box::new(MAINTENANCE_ACTIVE.clone()))
+            .unwrap();
+    }
+    if bitmask & constants::BITMASK_LICENSES == constants::BITMASK_LICENSES {
+        let registry = collector_registry(constants::BITMASK_LICENSES);
+        debug!("Registering registry LICENSE_TOTAL");
+        registry.register(Box::new(LICENSE_TOTAL.clone())).unwrap();
+        debug!("Registering registry LICENSE_USED");
+        registry.register(Box::new(LICENSE_USED.clone())).unwrap();
+        debug!("Registering registry LICENSE_FREE");
+        registry.register(Box::new(LICENSE_FREE.clone())).unwrap();
+        debug!("Registering registry LICENSE_RESERVED");
+        registry
+            .register(Box::new(LICENSE_RESERVED.clone()))
+            .unwrap();
+        debug!("Registering registry LICENSE_PENDING_JOBS");
+        registry
+            .register(Box::new(LICENSE_PENDING_JOBS.clone()))
+            .unwrap();
+        debug!("Registering registry LICENSE_REMOTE_COUNT");
+        registry
+            .register(Box::new(LICENSE_REMOTE_COUNT.clone()))
+            .unwrap();
+        debug!("Registering registry LICENSE_REMOTE_ALLOCATED");
+        registry
+            .register(Box::new(LICENSE_REMOTE_ALLOCATED.clone()))
+            .unwrap();
+    }
+    if bitmask & constants::BITMASK_NODE_REASONS == constants::BITMASK_NODE_REASONS {
+        let registry = collector_registry(constants::BITMASK_NODE_REASONS);
+        debug!("Registering registry NODE_REASON");
+        registry.register(Box::new(NODE_REASON.clone())).unwrap();
+        debug!("Registering registry NODE_REASON_SECONDS");
+        registry
+            .register(Box::new(NODE_REASON_SECONDS.clone()))
+            .unwrap();
+    }
+    if bitmask & constants::BITMASK_PARTITION_CONFIG == constants::BITMASK_PARTITION_CONFIG {
+        let registry = collector_registry(constants::BITMASK_PARTITION_CONFIG);
+        debug!("Registering registry PARTITION_STATE");
+        registry
+            .register(Box::new(PARTITION_STATE.clone()))
+            .unwrap();
+        debug!("Registering registry PARTITION_DEFAULT");
+        registry
+            .register(Box::new(PARTITION_DEFAULT.clone()))
+            .unwrap();
+        debug!("Registering registry PARTITION_NODES_CONFIGURED");
+        registry
+            .register(Box::new(PARTITION_NODES_CONFIGURED.clone()))
+            .unwrap();
+        debug!("Registering registry PARTITION_CPUS_CONFIGURED");
+        registry
+            .register(Box::new(PARTITION_CPUS_CONFIGURED.clone()))
+            .unwrap();
+        debug!("Registering registry PARTITION_MAX_TIME");
+        registry
+            .register(Box::new(PARTITION_MAX_TIME.clone()))
+            .unwrap();
+        debug!("Registering registry PARTITION_DEFAULT_TIME");
+        registry
+            .register(Box::new(PARTITION_DEFAULT_TIME.clone()))
+            .unwrap();
+        debug!("Registering registry PARTITION_MAX_NODES");
+        registry
+            .register(Box::new(PARTITION_MAX_NODES.clone()))
+            .unwrap();
+        debug!("Registering registry PARTITION_PRIORITY_TIER");
+        registry
+            .register(Box::new(PARTITION_PRIORITY_TIER.clone()))
+            .unwrap();
+        debug!("Registering registry PARTITION_PREEMPT_MODE");
+        registry
+            .register(Box::new(PARTITION_PREEMPT_MODE.clone()))
+            .unwrap();
+    }
+    if bitmask & constants::BITMASK_CONTROLLERS == constants::BITMASK_CONTROLLERS {
+        let registry = collector_registry(constants::BITMASK_CONTROLLERS);
+        debug!("Registering registry CONTROLLER_UP");
+        registry.register(Box::new(CONTROLLER_UP.clone())).unwrap();
+        debug!("Registering registry CONTROLLER_PING_DURATION");
+        registry
+            .register(Box::new(CONTROLLER_PING_DURATION.clone()))
+            .unwrap();
+    }
+}
+
+// Registry of the collector exporting the metrics enabled by bitmask
+fn collector_registry(bitmask: u32) -> &'static Registry {
+    for (name, mask) in constants::COLLECTORS.iter() {
+        if mask & bitmask == bitmask {
+            return &COLLECTOR_REGISTRIES[name];
+        }
+    }
+    panic!("BUG: no collector for bitmask {:#x}", bitmask);
+}
+
+fn update_collector(name: &str, cfg: &config::Configuration) -> Result<(), Box<dyn Error>> {
+    let slurm_cluster = &cfg.clusters;
+    match name {
+        constants::COLLECTOR_ACCOUNTING => sacct::update_accounting_metrics(slurm_cluster),
+        constants::COLLECTOR_CONTROLLERS => controllers::update_controller_metrics(slurm_cluster),
+        constants::COLLECTOR_GRES => gres::update_gres_metrics(slurm_cluster),
+        constants::COLLECTOR_JOBS => slurm::update_job_metrics(cfg),
+        constants::COLLECTOR_LICENSES => licenses::update_license_metrics(slurm_cluster),
+        constants::COLLECTOR_NODE_REASONS => reasons::update_node_reason_metrics(slurm_cluster),
+        constants::COLLECTOR_NODES => slurm::update_node_metrics(slurm_cluster),
+        constants::COLLECTOR_PARTITION_CONFIG => {
+            partitions::update_partition_config_metrics(slurm_cluster)
+        }
+        constants::COLLECTOR_PARTITIONS => slurm::update_partition_metrics(slurm_cluster),
+        constants::COLLECTOR_PRIORITY => sprio::update_priority_metrics(slurm_cluster),
+        constants::COLLECTOR_RESERVATIONS => {
+            reservations::update_reservation_metrics(slurm_cluster)
+        }
+        constants::COLLECTOR_SDIAG => sdiag::update_sdiag_metrics(slurm_cluster),
+        constants::COLLECTOR_SSHARE => sshare::update_share_metrics(slurm_cluster),
+        _ => bail!("unknown collector {}", name),
     }
 }
 
-pub fn metrics(slurm_cluster: &str, bitmask: u8) -> String {
+async fn collect(cfg: &config::Configuration) {
+    let _guard = SCRAPE_LOCK.lock().await;
+    run_collectors(cfg).await;
+}
+
+// Run all enabled collectors and store the encoded result as snapshot for the scrapes,
+// SCRAPE_LOCK must be held by the caller
+async fn run_collectors(cfg: &config::Configuration) {
+    let bitmask = cfg.bitmask;
     let encoder = TextEncoder::new();
     let mut buffer = String::new();
+    let mut families: Vec<MetricFamily> = Vec::new();
+    let mut running = Vec::new();
+    let now = chrono::Local::now().timestamp();
 
-    if bitmask & constants::BITMASK_PARTITIONS == constants::BITMASK_PARTITIONS {
-        if let Err(e) = slurm::update_partition_metrics(slurm_cluster) {
-            error!("Can't update SLURM partition metrics: {}", e);
-            return buffer;
+    // Work on a copy, the lock can't be held while waiting for the collectors
+    let mut status = match COLLECTOR_STATUS.lock() {
+        Ok(v) => v.clone(),
+        Err(e) => e.into_inner().clone(),
+    };
+
+    // Collectors are independent of each other and run concurrently on the blocking thread
+    // pool, so slow SLURM commands don't block the runtime. A failing collector only drops
+    // its own metrics from the scrape
+    for (name, mask) in constants::COLLECTORS.iter() {
+        if bitmask & mask == 0 {
+            continue;
+        }
+
+        // Collectors with a refresh interval keep the result of their last run until the
+        // interval has elapsed. Failed collectors are run again on the next collection
+        let interval = cfg.collector_intervals.get(*name).copied().unwrap_or(0);
+        if let Some((last_success, true)) = status.get(name) {
+            if now - last_success < interval - constants::COLLECTOR_INTERVAL_SLACK {
+                debug!(
+                    "exporter.rs:run_collectors: Collector {} ran {} seconds ago, using cached result (interval {} seconds)",
+                    name,
+                    now - last_success,
+                    interval
+                );
+                continue;
+            }
         }
+
+        let cfg = cfg.clone();
+        let task = task::spawn_blocking(move || {
+            let start = Instant::now();
+            let success = match update_collector(name, &cfg) {
+                Ok(_) => true,
+                Err(e) => {
+                    error!("Can't update SLURM metrics of collector {}: {}", name, e);
+                    false
+                }
+            };
+            (success, start.elapsed().as_secs_f64())
+        });
+        running.push((name, task));
     }
 
-    if let Err(e) = slurm::update_job_metrics(slurm_cluster, bitmask) {
-        error!("Can't update SLURM job metrics: {}", e);
-        return buffer;
+    for (name, task) in running {
+        let (success, elapsed) = match task.await {
+            Ok(v) => v,
+            Err(e) => {
+                error!("Collector {} failed: {}", name, e);
+                (false, 0.0)
+            }
+        };
+        debug!(
+            "exporter.rs:run_collectors: Collector {} finished in {} seconds, success: {}",
+            name, elapsed, success
+        );
+
+        COLLECTOR_DURATION.with_label_values(&[name]).set(elapsed);
+        COLLECTOR_SUCCESS
+            .with_label_values(&[name])
+            .set(success as i64);
+        if success {
+            COLLECTOR_LAST_UPDATE.with_label_values(&[name]).set(now);
+            status.insert(name, (now, true));
+        } else {
+            let last_success = status.get(name).map(|v| v.0).unwrap_or(0);
+            status.insert(name, (last_success, false));
+        }
+    }
+
+    // Fresh and cached results of all collectors whose last run was successful
+    for (name, mask) in constants::COLLECTORS.iter() {
+        if bitmask & mask == 0 {
+            continue;
+        }
+        if let Some((_, true)) = status.get(name) {
+            families.extend(COLLECTOR_REGISTRIES[name].gather());
+        }
     }
+    families.extend(REGISTRY.gather());
+
+    match COLLECTOR_STATUS.lock() {
+        Ok(mut v) => *v = status,
+        Err(e) => *e.into_inner() = status,
+    };
+
+    if let Err(e) = encoder.encode_utf8(&families, &mut buffer) {
+        error!("Can't encode metrics as UTF8 string: {}", e);
+    }
+
+    let mut snapshot = match SNAPSHOT.write() {
+        Ok(v) => v,
+        Err(e) => e.into_inner(),
+    };
+    *snapshot = (chrono::Local::now().timestamp(), buffer);
+    COLLECTIONS.fetch_add(1, Ordering::SeqCst);
+}
+
+// Collect metrics for a scrape. Scrapes arriving while a collection is running wait for it
+// and share its result instead of starting collections of their own
+async fn collect_for_scrape(cfg: &config::Configuration) {
+    let collections = COLLECTIONS.load(Ordering::SeqCst);
+    let guard = SCRAPE_LOCK.lock().await;
+
+    if COLLECTIONS.load(Ordering::SeqCst) != collections {
+        debug!("exporter.rs:collect_for_scrape: Using result of concurrent collection");
+        return;
+    }
+
+    if cfg.min_interval > 0 {
+        let timestamp = match SNAPSHOT.read() {
+            Ok(v) => v.0,
+            Err(e) => e.into_inner().0,
+        };
+        if chrono::Local::now().timestamp() - timestamp < cfg.min_interval {
+            debug!(
+                "exporter.rs:collect_for_scrape: Last collection is younger than {} seconds, using cached result",
+                cfg.min_interval
+            );
+            return;
+        }
+    }
+
+    // The collection runs in a task of its own which holds the lock until it is finished.
+    // A scrape cancelled by a timeout of Prometheus only stops waiting for the result,
+    // the next scrape waits for the running collection instead of starting another one
+    let cfg = cfg.clone();
+    let collection = tokio::spawn(async move {
+        let _guard = guard;
+        run_collectors(&cfg).await;
+    });
+    if let Err(e) = collection.await {
+        error!("Collection failed: {}", e);
+    }
+}
+
+pub async fn background_collection(cfg: config::Configuration) {
+    let mut interval = time::interval(Duration::from_secs(cfg.background_interval));
+    // don't try to catch up if a collection took longer than the interval
+    interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        debug!("exporter.rs:background_collection: Starting collection");
+        collect(&cfg).await;
+    }
+}
+
+pub async fn metrics(cfg: &config::Configuration) -> String {
+    let encoder = TextEncoder::new();
+
+    // Without background collection every scrape triggers a collection
+    if cfg.background_interval == 0 {
+        collect_for_scrape(cfg).await;
+    }
+
+    let (timestamp, mut buffer) = match SNAPSHOT.read() {
+        Ok(v) => v.clone(),
+        Err(e) => e.into_inner().clone(),
+    };
+
+    let now = chrono::Local::now().timestamp();
+    let stale = cfg.background_interval > 0
+        && now - timestamp > constants::STALE_COLLECTION_INTERVALS * cfg.background_interval as i64;
+    LAST_COLLECTION.set(timestamp);
+    COLLECTION_STALE.set(stale as i64);
 
-    if let Err(e) = encoder.encode_utf8(&REGISTRY.gather(), &mut buffer) {
+    if let Err(e) = encoder.encode_utf8(&STATUS_REGISTRY.gather(), &mut buffer) {
         error!("Can't encode metrics as UTF8 string: {}", e);
     }
 
@@ -0,0 +1,328 @@
+use crate::command;
+use crate::exporter;
+use crate::slurm;
+
+use log::debug;
+use std::collections::HashMap;
+use std::error::Error;
+
+// HashMap of
+//  <cluster>: {
+//   <partition>: {
+//       <gres>: <count>,
+//   },
+// }
+type GresCounts = HashMap<String, HashMap<String, HashMap<String, i64>>>;
+
+pub fn update_gres_metrics(slurm_cluster: &str) -> Result<(), Box<dyn Error>> {
+    // Default field width of 20 characters is too small for GRES strings of nodes
+    // with several GRES types and would truncate the output
+    let stdout = command::run_command(
+        "sinfo",
+        &[
+            "--noheader".to_string(),
+            "--Node".to_string(),
+            "--Format=Cluster,NodeHost,Partition,Gres:1024,GresUsed:1024".to_string(),
+            format!("--clusters={}", slurm_cluster),
+        ],
+    )?;
+    let (gres_configured, gres_allocated) = parse_node_gres(&stdout)?;
+
+    let stdout = command::run_command(
+        "squeue",
+        &[
+            "--noheader".to_string(),
+            "--Format=Cluster,Partition,NumNodes,ArrayTaskID:256,tres-per-node:1024".to_string(),
+            format!("--clusters={}", slurm_cluster),
+            "--states=PENDING".to_string(),
+            "--all".to_string(),
+        ],
+    )?;
+    let gres_pending = parse_pending_gres(&stdout)?;
+
+    exporter::PARTITION_GRES_CONFIGURED.reset();
+    exporter::PARTITION_GRES_ALLOCATED.reset();
+    exporter::PARTITION_GRES_IDLE.reset();
+    for (clu, cpart) in gres_configured.iter() {
+        for (part, pgres) in cpart.iter() {
+            for (gres, count) in pgres.iter() {
+                let used = gres_allocated
+                    .get(clu)
+                    .and_then(|v| v.get(part))
+                    .and_then(|v| v.get(gres))
+                    .copied()
+                    .unwrap_or(0);
+                debug!(
+                    "gres.rs:update_gres_metrics: Setting PARTITION_GRES_* {} {} {} -> {}/{}",
+                    clu, part, gres, *count, used
+                );
+                exporter::PARTITION_GRES_CONFIGURED
+                    .with_label_values(&[clu, part, gres])
+                    .set(*count);
+                exporter::PARTITION_GRES_ALLOCATED
+                    .with_label_values(&[clu, part, gres])
+                    .set(used);
+                exporter::PARTITION_GRES_IDLE
+                    .with_label_values(&[clu, part, gres])
+                    .set(*count - used);
+            }
+        }
+    }
+
+    exporter::JOBS_PENDING_GRES.reset();
+    for (clu, cpart) in gres_pending.iter() {
+        for (part, pgres) in cpart.iter() {
+            for (gres, count) in pgres.iter() {
+                debug!(
+                    "gres.rs:update_gres_metrics: Setting JOBS_PENDING_GRES {} {} {} -> {}",
+                    clu, part, gres, *count
+                );
+                exporter::JOBS_PENDING_GRES
+                    .with_label_values(&[clu, part, gres])
+                    .set(*count);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Aggregate configured and allocated GRES by partition from sinfo output
+fn parse_node_gres(stdout: &str) -> Result<(GresCounts, GresCounts), Box<dyn Error>> {
+    let mut gres_configured: GresCounts = HashMap::new();
+    let mut gres_allocated: GresCounts = HashMap::new();
+
+    for line in stdout.lines() {
+        debug!("gres.rs:parse_node_gres: Processing line: {}", line);
+        let (c, p, gres, gres_used) = match split_node_gres_line(line) {
+            Some(v) => v,
+            None => bail!(
+                "Can't extract tuple cluster, partition, gres and used gres from output '{}'",
+                line
+            ),
+        };
+
+        let cluster = gres_configured.entry(c.clone()).or_default();
+        let partition = cluster.entry(p.clone()).or_default();
+        for (g, count) in parse_gres(&gres) {
+            *partition.entry(g).or_insert(0) += count;
+        }
+
+        let cluster = gres_allocated.entry(c).or_default();
+        let partition = cluster.entry(p).or_default();
+        for (g, count) in parse_gres(&gres_used) {
+            *partition.entry(g).or_insert(0) += count;
+        }
+    }
+    Ok((gres_configured, gres_allocated))
+}
+
+// Aggregate the GRES requested by pending jobs from squeue output
+fn parse_pending_gres(stdout: &str) -> Result<GresCounts, Box<dyn Error>> {
+    let mut gres_pending: GresCounts = HashMap::new();
+
+    for line in stdout.lines() {
+        debug!("gres.rs:parse_pending_gres: Processing line: {}", line);
+        let (c, p, nodes, tres) = match split_job_gres_line(line) {
+            Some(v) => v,
+            None => bail!(
+                "Can't extract tuple cluster, partition, nodes and tres-per-node from output '{}'",
+                line
+            ),
+        };
+        let requested = parse_tres_per_node(&tres);
+        if requested.is_empty() {
+            continue;
+        }
+
+        // a pending job can list several partitions, requested resources are accounted for each of them
+        for part in p.split(',') {
+            let cluster = gres_pending.entry(c.clone()).or_default();
+            let partition = cluster.entry(part.to_string()).or_default();
+            for (g, count) in requested.iter() {
+                *partition.entry(g.to_string()).or_insert(0) += count * nodes;
+            }
+        }
+    }
+    Ok(gres_pending)
+}
+
+fn split_node_gres_line(s: &str) -> Option<(String, String, String, String)> {
+    let fields: Vec<&str> = s.split_whitespace().collect();
+    if fields.len() != 5 {
+        return None;
+    }
+    Some((
+        fields[0].to_string(),
+        // * marks the default partition
+        fields[2].trim_end_matches('*').to_string(),
+        fields[3].to_string(),
+        fields[4].to_string(),
+    ))
+}
+
+fn split_job_gres_line(s: &str) -> Option<(String, String, i64, String)> {
+    let fields: Vec<&str> = s.split_whitespace().collect();
+    if fields.len() != 5 {
+        return None;
+    }
+    // pending array tasks are reported as a single entry, account the nodes of every task
+    let nodes = fields[2].parse::<i64>().ok()? * slurm::array_task_count(fields[3]);
+    Some((
+        fields[0].to_string(),
+        fields[1].to_string(),
+        nodes,
+        fields[4].to_string(),
+    ))
+}
+
+// Parse GRES strings as reported by sinfo's Gres and GresUsed fields, e.g.
+//   gpu:a100:4(S:0-1),gpu:v100:2
+//   gpu:a100:2(IDX:0-1),gpu:v100:0(IDX:N/A)
+// into a list of (<name>[:<type>], <count>)
+fn parse_gres(s: &str) -> Vec<(String, i64)> {
+    let mut result: Vec<(String, i64)> = Vec::new();
+
+    if s == "(null)" || s == "N/A" {
+        return result;
+    }
+
+    for entry in split_outside_parentheses(s) {
+        // strip socket binding or index information
+        let entry = match entry.split_once('(') {
+            Some((v, _)) => v,
+            None => entry,
+        };
+        if entry.is_empty() {
+            continue;
+        }
+
+        let mut parts: Vec<&str> = entry.split(':').filter(|v| *v != "no_consume").collect();
+        let count = match parts.last().and_then(|v| parse_gres_count(v)) {
+            Some(v) => {
+                parts.pop();
+                v
+            }
+            None => 1,
+        };
+        if parts.is_empty() {
+            continue;
+        }
+        result.push((parts.join(":"), count));
+    }
+    result
+}
+
+// Parse squeue's tres-per-node field. Depending on the SLURM version GRES are reported as
+// gres:gpu:a100:2 or gres/gpu:a100:2 (or gres/gpu:a100=2), other TRES types are skipped
+fn parse_tres_per_node(s: &str) -> Vec<(String, i64)> {
+    let mut result: Vec<(String, i64)> = Vec::new();
+
+    if s == "N/A" || s == "(null)" {
+        return result;
+    }
+
+    for entry in s.split(',') {
+        let gres = match entry
+            .strip_prefix("gres:")
+            .or_else(|| entry.strip_prefix("gres/"))
+        {
+            Some(v) => v,
+            None => continue,
+        };
+        let gres = gres.replacen('=', ":", 1);
+        result.extend(parse_gres(&gres));
+    }
+    result
+}
+
+fn parse_gres_count(s: &str) -> Option<i64> {
+    let (number, factor) = match s.chars().last()? {
+        'K' | 'k' => (&s[..s.len() - 1], 1024),
+        'M' | 'm' => (&s[..s.len() - 1], 1024 * 1024),
+        'G' | 'g' => (&s[..s.len() - 1], 1024 * 1024 * 1024),
+        'T' | 't' => (&s[..s.len() - 1], 1024 * 1024 * 1024 * 1024),
+        _ => (s, 1),
+    };
+    number.parse::<i64>().ok().map(|v| v * factor)
+}
+
+fn split_outside_parentheses(s: &str) -> Vec<&str> {
+    let mut result: Vec<&str> = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                result.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    result.push(&s[start..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(counts: &GresCounts, partition: &str, gres: &str) -> Option<i64> {
+        counts.get("c1")?.get(partition)?.get(gres).copied()
+    }
+
+    #[test]
+    fn test_parse_gres() {
+        assert_eq!(
+            parse_gres("gpu:a100:4(S:0-1)"),
+            vec![("gpu:a100".to_string(), 4)]
+        );
+        assert_eq!(
+            parse_gres("gpu:a100:2(IDX:0-1),gpu:v100:0(IDX:N/A)"),
+            vec![("gpu:a100".to_string(), 2), ("gpu:v100".to_string(), 0)]
+        );
+        assert!(parse_gres("(null)").is_empty());
+    }
+
+    #[test]
+    fn test_parse_tres_per_node() {
+        assert_eq!(
+            parse_tres_per_node("gres/gpu=2"),
+            vec![("gpu".to_string(), 2)]
+        );
+        assert_eq!(
+            parse_tres_per_node("gres:gpu:a100:2"),
+            vec![("gpu:a100".to_string(), 2)]
+        );
+        assert!(parse_tres_per_node("(null)").is_empty());
+    }
+
+    #[test]
+    fn test_parse_node_gres() {
+        let (configured, allocated) =
+            parse_node_gres(include_str!("../tests/fixtures/sinfo_gres.txt")).unwrap();
+
+        assert_eq!(count(&configured, "gpu", "gpu:a100"), Some(8));
+        assert_eq!(count(&configured, "gpu", "gpu:v100"), Some(2));
+        assert_eq!(count(&allocated, "gpu", "gpu:a100"), Some(2));
+        assert_eq!(count(&allocated, "gpu", "gpu:v100"), Some(1));
+        assert_eq!(count(&configured, "batch", "gpu:a100"), Some(4));
+        assert_eq!(count(&allocated, "batch", "gpu:v100"), Some(1));
+        assert_eq!(configured["c1"]["batch"].len(), 2);
+    }
+
+    #[test]
+    fn test_parse_pending_gres() {
+        let pending =
+            parse_pending_gres(include_str!("../tests/fixtures/squeue_gres.txt")).unwrap();
+
+        assert_eq!(count(&pending, "gpu", "gpu"), Some(4));
+        // 4 array tasks with 2 GPUs each plus a job pending in two partitions
+        assert_eq!(count(&pending, "gpu", "gpu:a100"), Some(9));
+        assert_eq!(count(&pending, "batch", "gpu:a100"), Some(1));
+        assert_eq!(pending["c1"]["batch"].len(), 1);
+    }
+}
@@ -0,0 +1,245 @@
+use crate::command;
+use crate::exporter;
+use crate::slurm;
+
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::error::Error;
+
+pub struct LicenseData {
+    pub cluster: String,
+    pub name: String,
+    pub total: Option<i64>,
+    pub used: Option<i64>,
+    pub free: Option<i64>,
+    pub reserved: Option<i64>,
+}
+
+pub struct RemoteLicenseData {
+    pub name: String,
+    pub server: String,
+    pub count: Option<i64>,
+    pub allocated: Option<f64>,
+}
+
+pub fn update_license_metrics(slurm_cluster: &str) -> Result<(), Box<dyn Error>> {
+    let mut licenses: Vec<LicenseData> = Vec::new();
+    // HashMap of
+    //  [<cluster>, <license>]: <number of pending jobs>
+    let mut pending: HashMap<Vec<String>, i64> = HashMap::new();
+
+    let collected = slurm::collect_clusters(slurm_cluster, |cluster| {
+        let stdout = command::run_command(
+            "scontrol",
+            &[
+                "--oneliner".to_string(),
+                format!("--clusters={}", cluster),
+                "show".to_string(),
+                "licenses".to_string(),
+            ],
+        )?;
+        let mut cluster_licenses: Vec<LicenseData> = Vec::new();
+        for line in stdout.lines() {
+            debug!(
+                "licenses.rs:update_license_metrics: Processing line: {}",
+                line
+            );
+            let kv = slurm::split_key_value_line(line);
+            // skips "No licenses configured" and cluster headers
+            let name = match kv.get("LicenseName") {
+                Some(v) => v,
+                None => continue,
+            };
+            let number = |key: &str| -> Option<i64> { kv.get(key)?.parse::<i64>().ok() };
+            cluster_licenses.push(LicenseData {
+                cluster: cluster.to_string(),
+                name: name.to_string(),
+                total: number("Total"),
+                used: number("Used"),
+                free: number("Free"),
+                reserved: number("Reserved"),
+            });
+        }
+
+        // %r: reason, %K: array task ids, %W: licenses (last, because a request may contain |
+        // for alternatives)
+        let stdout = command::run_command(
+            "squeue",
+            &[
+                "--noheader".to_string(),
+                "--all".to_string(),
+                "--states=PENDING".to_string(),
+                "--format=%r|%K|%W".to_string(),
+                format!("--clusters={}", cluster),
+            ],
+        )?;
+        let mut cluster_pending: HashMap<String, i64> = HashMap::new();
+        for line in stdout.lines() {
+            debug!(
+                "licenses.rs:update_license_metrics: Processing line: {}",
+                line
+            );
+            if line.starts_with("CLUSTER:") {
+                continue;
+            }
+            let fields: Vec<&str> = line.splitn(3, '|').collect();
+            if fields.len() != 3 {
+                bail!("Can't extract requested licenses from output '{}'", line);
+            }
+            let (reason, tasks, requested) = (fields[0], fields[1], fields[2]);
+            if reason.trim() != "Licenses" {
+                continue;
+            }
+            for name in parse_license_request(requested) {
+                *cluster_pending.entry(name).or_insert(0) += slurm::array_task_count(tasks);
+            }
+        }
+        Ok((cluster_licenses, cluster_pending))
+    })?;
+
+    for (cluster, (cluster_licenses, cluster_pending)) in collected {
+        licenses.extend(cluster_licenses);
+        for (name, count) in cluster_pending {
+            *pending.entry(vec![cluster.clone(), name]).or_insert(0) += count;
+        }
+    }
+
+    // Remote licenses are stored in the SLURM database, a missing slurmdbd should not
+    // hide the local license information
+    let remote = match fetch_remote_licenses() {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("Can't fetch remote licenses from SLURM database: {}", e);
+            Vec::new()
+        }
+    };
+
+    exporter::LICENSE_TOTAL.reset();
+    exporter::LICENSE_USED.reset();
+    exporter::LICENSE_FREE.reset();
+    exporter::LICENSE_RESERVED.reset();
+    exporter::LICENSE_PENDING_JOBS.reset();
+    exporter::LICENSE_REMOTE_COUNT.reset();
+    exporter::LICENSE_REMOTE_ALLOCATED.reset();
+
+    for lic in licenses.iter() {
+        debug!(
+            "licenses.rs:update_license_metrics: Setting license metrics for {} {}",
+            lic.cluster, lic.name
+        );
+        let labels = [lic.cluster.as_str(), lic.name.as_str()];
+        if let Some(v) = lic.total {
+            exporter::LICENSE_TOTAL.with_label_values(&labels).set(v);
+        }
+        if let Some(v) = lic.used {
+            exporter::LICENSE_USED.with_label_values(&labels).set(v);
+        }
+        if let Some(v) = lic.free {
+            exporter::LICENSE_FREE.with_label_values(&labels).set(v);
+        }
+        if let Some(v) = lic.reserved {
+            exporter::LICENSE_RESERVED.with_label_values(&labels).set(v);
+        }
+        // always export the number of pending jobs for configured licenses, even if it is 0
+        exporter::LICENSE_PENDING_JOBS
+            .with_label_values(&labels)
+            .set(0);
+    }
+
+    for (labels, count) in pending.iter() {
+        debug!(
+            "licenses.rs:update_license_metrics: Setting LICENSE_PENDING_JOBS {} -> {}",
+            labels.join(" "),
+            count
+        );
+        exporter::LICENSE_PENDING_JOBS
+            .with_label_values(&[&labels[0], &labels[1]])
+            .set(*count);
+    }
+
+    for lic in remote.iter() {
+        debug!(
+            "licenses.rs:update_license_metrics: Setting remote license metrics for {}@{}",
+            lic.name, lic.server
+        );
+        let labels = [lic.name.as_str(), lic.server.as_str()];
+        if let Some(v) = lic.count {
+            exporter::LICENSE_REMOTE_COUNT
+                .with_label_values(&labels)
+                .set(v);
+        }
+        if let Some(v) = lic.allocated {
+            exporter::LICENSE_REMOTE_ALLOCATED
+                .with_label_values(&labels)
+                .set(v);
+        }
+    }
+    Ok(())
+}
+
+fn fetch_remote_licenses() -> Result<Vec<RemoteLicenseData>, Box<dyn Error>> {
+    let mut result: Vec<RemoteLicenseData> = Vec::new();
+    let mut header: Vec<String> = Vec::new();
+
+    let stdout = command::run_command(
+        "sacctmgr",
+        &[
+            "--parsable2".to_string(),
+            "show".to_string(),
+            "resource".to_string(),
+            "type=license".to_string(),
+        ],
+    )?;
+
+    for line in stdout.lines() {
+        debug!(
+            "licenses.rs:fetch_remote_licenses: Processing line: {}",
+            line
+        );
+        let fields: Vec<&str> = line.split('|').map(|v| v.trim()).collect();
+
+        if header.is_empty() {
+            if fields.first() == Some(&"Name") {
+                header = fields.iter().map(|v| v.to_string()).collect();
+            }
+            continue;
+        }
+
+        if fields.len() < header.len() {
+            bail!("Can't extract remote license data from output '{}'", line);
+        }
+
+        // Depending on the SLURM version the percentage is reported as "% Allocated" or "Allocated"
+        let field = |names: &[&str]| -> Option<&str> {
+            let idx = header.iter().position(|v| names.contains(&v.as_str()))?;
+            Some(fields[idx])
+        };
+
+        result.push(RemoteLicenseData {
+            name: field(&["Name"]).unwrap_or_default().to_string(),
+            server: field(&["Server"]).unwrap_or_default().to_string(),
+            count: field(&["Count"]).and_then(|v| v.parse::<i64>().ok()),
+            allocated: field(&["% Allocated", "Allocated"]).and_then(|v| v.parse::<f64>().ok()),
+        });
+    }
+    Ok(result)
+}
+
+// Licenses requested by a job, e.g. "matlab:2,ansys@db:1" or "fluent|abaqus"
+fn parse_license_request(s: &str) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+
+    for lic in s.split([',', '|']).map(|v| v.trim()) {
+        if lic.is_empty() || lic == "(null)" {
+            continue;
+        }
+        let name = match lic.split_once([':', '*']) {
+            Some((n, _)) => n,
+            None => lic,
+        };
+        if !result.iter().any(|v| v == name) {
+            result.push(name.to_string());
+        }
+    }
+    result
+}
@@ -1,14 +1,27 @@
 #[macro_use]
 extern crate simple_error;
 
+mod command;
+mod config;
 mod constants;
+mod controllers;
 mod exporter;
+mod gres;
+mod licenses;
 mod logging;
+mod partitions;
+mod reasons;
+mod reservations;
+mod sacct;
+mod sdiag;
 mod slurm;
+mod sprio;
+mod sshare;
 mod usage;
 
 use getopts::Options;
 use log::error;
+use std::collections::HashMap;
 use std::error::Error;
 use std::net::ToSocketAddrs;
 use std::{env, process};
@@ -20,22 +33,118 @@ async fn main() {
     let mut options = Options::new();
     let mut log_level = log::LevelFilter::Info;
 
+    let mut fairshare = true;
+    let mut gres = true;
+    let mut accounting = false;
+    let mut controllers = true;
     let mut job_cpus = true;
     let mut job_count = true;
     let mut job_nodes = true;
+    let mut job_runtime = true;
     let mut job_tasks = true;
+    let mut licenses = true;
+    let mut node_reasons = true;
+    let mut nodes = true;
+    let mut partition_config = true;
     let mut partitions = true;
+    let mut pending_reasons = true;
+    let mut pending_wait = true;
+    let mut priority = true;
+    let mut reservations = true;
+    let mut sdiag = true;
 
+    options.optflag(
+        "A",
+        "accounting",
+        "Export finished jobs from SLURM accounting",
+    );
     options.optflag("C", "no-job-cpus", "Don't export job CPUs");
     options.optflag("D", "debug", "Enable debug mode");
+    options.optflag(
+        "E",
+        "no-job-runtime",
+        "Don't export runtime and time limit usage of running jobs",
+    );
+    options.optflag("F", "no-fairshare", "Don't export fairshare information");
+    options.optflag("G", "no-gres", "Don't export generic resources");
     options.optflag("J", "no-job-count", "Don't export number of jobs");
+    options.optflag("L", "no-licenses", "Don't export license usage");
     options.optflag("N", "no-job-nodes", "Don't export number of nodes for jobs");
-    options.optflag("P", "no-partitions", "Don't export partition states");
+    options.optflag(
+        "P",
+        "no-partitions",
+        "Don't export partition states and CPUs",
+    );
+    options.optflag(
+        "R",
+        "no-pending-reasons",
+        "Don't export pending jobs by pending reason",
+    );
+    options.optflag("S", "no-sdiag", "Don't export scheduler statistics");
     options.optflag("T", "no-job-tasks", "Don't export number of tasks for jobs");
+    options.optflag(
+        "W",
+        "no-pending-wait",
+        "Don't export wait times of pending jobs",
+    );
     options.optflag("V", "version", "Show version information");
+    options.optflag(
+        "d",
+        "no-node-reasons",
+        "Don't export reasons of drained or down nodes",
+    );
     options.optflag("h", "help", "Show help text");
+    options.optflag("p", "no-priority", "Don't export job priorities");
     options.optflag("q", "quiet", "Quiet operation");
+    options.optflag(
+        "m",
+        "no-controllers",
+        "Don't export controller availability",
+    );
+    options.optflag("n", "no-nodes", "Don't export per-node metrics");
+    options.optflag("r", "no-reservations", "Don't export reservations");
+    options.optflag(
+        "s",
+        "no-partition-config",
+        "Don't export partition configuration",
+    );
+    options.optopt(
+        "o",
+        "command-timeout",
+        "seconds",
+        "Timeout for SLURM commands",
+    );
+    options.optopt(
+        "t",
+        "time-limit-threshold",
+        "minutes",
+        "Count running jobs within <minutes> of their time limit",
+    );
+    options.optopt(
+        "b",
+        "background-interval",
+        "seconds",
+        "Collect metrics in the background every <seconds>",
+    );
     options.optopt("c", "cluster", "cluster", "Export data for given cluster");
+    options.optopt(
+        "I",
+        "min-interval",
+        "seconds",
+        "Minimal interval between collections triggered by scrapes",
+    );
+    options.optopt(
+        "j",
+        "job-labels",
+        "label,...",
+        "Label dimensions of job metrics",
+    );
+    options.optopt(
+        "u",
+        "collector-intervals",
+        "collector=seconds,...",
+        "Refresh intervals of collectors",
+    );
     options.optopt(
         "l",
         "listen",
@@ -67,26 +176,82 @@ async fn main() {
         log_level = log::LevelFilter::Warn;
     }
 
+    if opts.opt_present("A") {
+        accounting = true;
+    }
+
     if opts.opt_present("C") {
         job_cpus = false;
     }
 
+    if opts.opt_present("d") {
+        node_reasons = false;
+    }
+
+    if opts.opt_present("E") {
+        job_runtime = false;
+    }
+
+    if opts.opt_present("F") {
+        fairshare = false;
+    }
+
+    if opts.opt_present("G") {
+        gres = false;
+    }
+
     if opts.opt_present("J") {
         job_count = false;
     }
 
+    if opts.opt_present("L") {
+        licenses = false;
+    }
+
     if opts.opt_present("N") {
         job_nodes = false;
     }
 
+    if opts.opt_present("m") {
+        controllers = false;
+    }
+
+    if opts.opt_present("n") {
+        nodes = false;
+    }
+
+    if opts.opt_present("p") {
+        priority = false;
+    }
+
     if opts.opt_present("P") {
         partitions = false;
     }
 
+    if opts.opt_present("r") {
+        reservations = false;
+    }
+
+    if opts.opt_present("R") {
+        pending_reasons = false;
+    }
+
+    if opts.opt_present("s") {
+        partition_config = false;
+    }
+
+    if opts.opt_present("S") {
+        sdiag = false;
+    }
+
     if opts.opt_present("T") {
         job_tasks = false;
     }
 
+    if opts.opt_present("W") {
+        pending_wait = false;
+    }
+
     let clusters = opts
         .opt_str("c")
         .unwrap_or_else(|| constants::SLURM_CLUSTERS.to_string());
@@ -95,6 +260,63 @@ async fn main() {
         .opt_str("l")
         .unwrap_or_else(|| constants::DEFAULT_LISTEN_ADDRESS.to_string());
 
+    let job_labels = opts
+        .opt_str("j")
+        .unwrap_or_else(|| constants::DEFAULT_JOB_LABELS.to_string());
+
+    let time_limit_threshold = match opts.opt_str("t") {
+        Some(v) => match v.parse::<i64>() {
+            Ok(v) if v >= 0 => v,
+            _ => {
+                eprintln!("Error: Invalid time limit threshold {}", v);
+                process::exit(1);
+            }
+        },
+        None => constants::DEFAULT_TIME_LIMIT_THRESHOLD,
+    };
+
+    let background_interval = match opts.opt_str("b") {
+        Some(v) => match v.parse::<u64>() {
+            Ok(v) if v > 0 => v,
+            _ => {
+                eprintln!("Error: Invalid background collection interval {}", v);
+                process::exit(1);
+            }
+        },
+        None => 0,
+    };
+
+    let collector_intervals =
+        match parse_collector_intervals(&opts.opt_str("u").unwrap_or_default()) {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("Error: Invalid collector intervals: {}", e);
+                process::exit(1);
+            }
+        };
+
+    let min_interval = match opts.opt_str("I") {
+        Some(v) => match v.parse::<i64>() {
+            Ok(v) if v >= 0 => v,
+            _ => {
+                eprintln!("Error: Invalid minimal collection interval {}", v);
+                process::exit(1);
+            }
+        },
+        None => 0,
+    };
+
+    let command_timeout = match opts.opt_str("o") {
+        Some(v) => match v.parse::<u64>() {
+            Ok(v) if v > 0 => v,
+            _ => {
+                eprintln!("Error: Invalid command timeout {}", v);
+                process::exit(1);
+            }
+        },
+        None => constants::DEFAULT_COMMAND_TIMEOUT,
+    };
+
     match logging::init(log_level) {
         Ok(_) => {}
         Err(e) => {
@@ -103,7 +325,7 @@ async fn main() {
         }
     };
 
-    let mut export_bitmask: u8 = 0x00;
+    let mut export_bitmask: u32 = 0x00;
     if job_cpus {
         export_bitmask |= constants::BITMASK_JOB_CPUS;
     }
@@ -119,9 +341,69 @@ async fn main() {
     if partitions {
         export_bitmask |= constants::BITMASK_PARTITIONS;
     }
+    if nodes {
+        export_bitmask |= constants::BITMASK_NODES;
+    }
+    if gres {
+        export_bitmask |= constants::BITMASK_GRES;
+    }
+    if pending_reasons {
+        export_bitmask |= constants::BITMASK_PENDING_REASONS;
+    }
+    if pending_wait {
+        export_bitmask |= constants::BITMASK_PENDING_WAIT;
+    }
+    if job_runtime {
+        export_bitmask |= constants::BITMASK_JOB_RUNTIME;
+    }
+    if sdiag {
+        export_bitmask |= constants::BITMASK_SDIAG;
+    }
+    if fairshare {
+        export_bitmask |= constants::BITMASK_SSHARE;
+    }
+    if accounting {
+        export_bitmask |= constants::BITMASK_ACCOUNTING;
+    }
+    if priority {
+        export_bitmask |= constants::BITMASK_PRIORITY;
+    }
+    if reservations {
+        export_bitmask |= constants::BITMASK_RESERVATIONS;
+    }
+    if licenses {
+        export_bitmask |= constants::BITMASK_LICENSES;
+    }
+    if node_reasons {
+        export_bitmask |= constants::BITMASK_NODE_REASONS;
+    }
+    if partition_config {
+        export_bitmask |= constants::BITMASK_PARTITION_CONFIG;
+    }
+    if controllers {
+        export_bitmask |= constants::BITMASK_CONTROLLERS;
+    }
+
+    match parse_job_labels(&job_labels) {
+        Ok(v) => exporter::set_job_labels(v),
+        Err(e) => {
+            error!("Invalid job labels {}: {}", job_labels, e);
+            process::exit(1);
+        }
+    };
 
+    command::set_command_timeout(command_timeout);
     exporter::register(export_bitmask);
 
+    let configuration = config::Configuration {
+        clusters,
+        bitmask: export_bitmask,
+        time_limit_threshold: 60 * time_limit_threshold,
+        background_interval,
+        min_interval,
+        collector_intervals,
+    };
+
     let socketaddr = match socketaddr_from_listen(&listen_address) {
         Ok(v) => v,
         Err(e) => {
@@ -130,9 +412,16 @@ async fn main() {
         }
     };
 
+    if background_interval > 0 {
+        tokio::spawn(exporter::background_collection(configuration.clone()));
+    }
+
     let prometheus_route = warp::path(constants::DEFAULT_METRICS_PATH)
         .and(warp::get())
-        .map(move || exporter::metrics(&clusters, export_bitmask));
+        .then(move || {
+            let configuration = configuration.clone();
+            async move { exporter::metrics(&configuration).await }
+        });
 
     let root_route = warp::path::end()
         .and(warp::get())
@@ -150,3 +439,63 @@ fn socketaddr_from_listen(listen: &str) -> Result<std::net::SocketAddr, Box<dyn
     }
     Ok(addresses[0])
 }
+
+fn parse_job_labels(labels: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut result: Vec<String> = Vec::new();
+    for label in labels
+        .split(',')
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+    {
+        if !constants::SUPPORTED_JOB_LABELS.contains(&label) {
+            bail!(
+                "unsupported label {}, supported labels are {}",
+                label,
+                constants::SUPPORTED_JOB_LABELS.join(", ")
+            );
+        }
+        if result.iter().any(|v| v == label) {
+            bail!("label {} is used more than once", label);
+        }
+        result.push(label.to_string());
+    }
+    Ok(result)
+}
+
+fn parse_collector_intervals(intervals: &str) -> Result<HashMap<String, i64>, Box<dyn Error>> {
+    let mut result: HashMap<String, i64> = HashMap::new();
+    // sacct is expensive, the accounting collector runs on a longer interval unless
+    // configured otherwise
+    result.insert(
+        constants::COLLECTOR_ACCOUNTING.to_string(),
+        constants::DEFAULT_ACCOUNTING_INTERVAL,
+    );
+
+    for entry in intervals
+        .split(',')
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+    {
+        let (collector, interval) = match entry.split_once('=') {
+            Some(v) => v,
+            None => bail!("{} is not in the format <collector>=<seconds>", entry),
+        };
+        if !constants::COLLECTORS.iter().any(|(n, _)| *n == collector) {
+            bail!(
+                "unsupported collector {}, supported collectors are {}",
+                collector,
+                constants::COLLECTORS
+                    .iter()
+                    .map(|(n, _)| *n)
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            );
+        }
+        let interval = match interval.parse::<i64>() {
+            Ok(v) if v >= 0 => v,
+            _ => bail!("invalid interval {} for collector {}", interval, collector),
+        };
+        result.insert(collector.to_string(), interval);
+    }
+    Ok(result)
+}
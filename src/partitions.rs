@@ -0,0 +1,130 @@
+use crate::command;
+use crate::constants;
+use crate::exporter;
+use crate::slurm;
+
+use log::debug;
+use std::error::Error;
+
+pub struct PartitionConfigData {
+    pub cluster: String,
+    pub name: String,
+    pub state: String,
+    pub default: bool,
+    pub nodes: Option<i64>,
+    pub cpus: Option<i64>,
+    pub max_time: Option<i64>,
+    pub default_time: Option<i64>,
+    pub max_nodes: Option<i64>,
+    pub priority_tier: Option<i64>,
+    pub preempt_mode: String,
+}
+
+pub fn update_partition_config_metrics(slurm_cluster: &str) -> Result<(), Box<dyn Error>> {
+    let partitions: Vec<PartitionConfigData> = slurm::collect_clusters(slurm_cluster, |cluster| {
+        let stdout = command::run_command(
+            "scontrol",
+            &[
+                "--oneliner".to_string(),
+                format!("--clusters={}", cluster),
+                "show".to_string(),
+                "partition".to_string(),
+            ],
+        )?;
+        let mut result: Vec<PartitionConfigData> = Vec::new();
+        for line in stdout.lines() {
+            debug!(
+                "partitions.rs:update_partition_config_metrics: Processing line: {}",
+                line
+            );
+            let kv = slurm::split_key_value_line(line);
+            // skips cluster headers
+            let name = match kv.get("PartitionName") {
+                Some(v) => v,
+                None => continue,
+            };
+            let number = |key: &str| -> Option<i64> { kv.get(key)?.parse::<i64>().ok() };
+            result.push(PartitionConfigData {
+                cluster: cluster.to_string(),
+                name: name.to_string(),
+                state: kv.get("State").cloned().unwrap_or_default(),
+                default: kv.get("Default").map(|v| v == "YES").unwrap_or(false),
+                nodes: number("TotalNodes"),
+                cpus: number("TotalCPUs"),
+                // MaxTime and DefaultTime are durations or UNLIMITED/NONE
+                max_time: kv.get("MaxTime").and_then(|v| slurm::parse_duration(v)),
+                default_time: kv.get("DefaultTime").and_then(|v| slurm::parse_duration(v)),
+                // UNLIMITED for partitions without node limit
+                max_nodes: number("MaxNodes"),
+                priority_tier: number("PriorityTier"),
+                preempt_mode: kv.get("PreemptMode").cloned().unwrap_or_default(),
+            });
+        }
+        Ok(result)
+    })?
+    .into_iter()
+    .flat_map(|(_, v)| v)
+    .collect();
+
+    exporter::PARTITION_STATE.reset();
+    exporter::PARTITION_DEFAULT.reset();
+    exporter::PARTITION_NODES_CONFIGURED.reset();
+    exporter::PARTITION_CPUS_CONFIGURED.reset();
+    exporter::PARTITION_MAX_TIME.reset();
+    exporter::PARTITION_DEFAULT_TIME.reset();
+    exporter::PARTITION_MAX_NODES.reset();
+    exporter::PARTITION_PRIORITY_TIER.reset();
+    exporter::PARTITION_PREEMPT_MODE.reset();
+
+    for p in partitions.iter() {
+        debug!(
+            "partitions.rs:update_partition_config_metrics: Setting partition configuration metrics for {} {}",
+            p.cluster, p.name
+        );
+        let labels = [p.cluster.as_str(), p.name.as_str()];
+        for state in constants::PARTITION_STATES.iter() {
+            exporter::PARTITION_STATE
+                .with_label_values(&[&p.cluster, &p.name, state])
+                .set((&p.state == state) as i64);
+        }
+        exporter::PARTITION_DEFAULT
+            .with_label_values(&labels)
+            .set(p.default as i64);
+        if let Some(v) = p.nodes {
+            exporter::PARTITION_NODES_CONFIGURED
+                .with_label_values(&labels)
+                .set(v);
+        }
+        if let Some(v) = p.cpus {
+            exporter::PARTITION_CPUS_CONFIGURED
+                .with_label_values(&labels)
+                .set(v);
+        }
+        if let Some(v) = p.max_time {
+            exporter::PARTITION_MAX_TIME
+                .with_label_values(&labels)
+                .set(v);
+        }
+        if let Some(v) = p.default_time {
+            exporter::PARTITION_DEFAULT_TIME
+                .with_label_values(&labels)
+                .set(v);
+        }
+        if let Some(v) = p.max_nodes {
+            exporter::PARTITION_MAX_NODES
+                .with_label_values(&labels)
+                .set(v);
+        }
+        if let Some(v) = p.priority_tier {
+            exporter::PARTITION_PRIORITY_TIER
+                .with_label_values(&labels)
+                .set(v);
+        }
+        if !p.preempt_mode.is_empty() {
+            exporter::PARTITION_PREEMPT_MODE
+                .with_label_values(&[&p.cluster, &p.name, &p.preempt_mode])
+                .set(1);
+        }
+    }
+    Ok(())
+}
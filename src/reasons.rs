@@ -0,0 +1,88 @@
+use crate::command;
+use crate::exporter;
+use crate::slurm;
+
+use log::debug;
+use std::collections::HashSet;
+use std::error::Error;
+
+pub struct NodeReasonData {
+    pub cluster: String,
+    pub node: String,
+    pub state: String,
+    pub timestamp: Option<i64>,
+    pub user: String,
+    pub reason: String,
+}
+
+pub fn update_node_reason_metrics(slurm_cluster: &str) -> Result<(), Box<dyn Error>> {
+    let mut reasons: Vec<NodeReasonData> = Vec::new();
+    // HashSet of
+    //  (<cluster>, <node>)
+    let mut seen: HashSet<(String, String)> = HashSet::new();
+
+    // Reason must be the last field because it may contain whitespaces
+    let stdout = command::run_command(
+        "sinfo",
+        &[
+            "--noheader".to_string(),
+            "--Node".to_string(),
+            "--list-reasons".to_string(),
+            "--Format=Cluster,NodeList:128,StateLong,Timestamp,User:64,Reason:256".to_string(),
+            format!("--clusters={}", slurm_cluster),
+        ],
+    )?;
+    for line in stdout.lines() {
+        debug!(
+            "reasons.rs:update_node_reason_metrics: Processing line: {}",
+            line
+        );
+        let r = match split_node_reason_line(line) {
+            Some(v) => v,
+            None => bail!("Can't extract node reason from output '{}'", line),
+        };
+        // with --Node, sinfo reports a node once for every partition it belongs to
+        if !seen.insert((r.cluster.clone(), r.node.clone())) {
+            continue;
+        }
+        reasons.push(r);
+    }
+
+    let now = chrono::Local::now().timestamp();
+
+    exporter::NODE_REASON.reset();
+    exporter::NODE_REASON_SECONDS.reset();
+
+    for r in reasons.iter() {
+        debug!(
+            "reasons.rs:update_node_reason_metrics: Setting node reason metrics for {} {}",
+            r.cluster, r.node
+        );
+        exporter::NODE_REASON
+            .with_label_values(&[&r.cluster, &r.node, &r.state, &r.reason, &r.user])
+            .set(1);
+        if let Some(v) = r.timestamp {
+            exporter::NODE_REASON_SECONDS
+                .with_label_values(&[&r.cluster, &r.node])
+                .set(now - v);
+        }
+    }
+    Ok(())
+}
+
+fn split_node_reason_line(s: &str) -> Option<NodeReasonData> {
+    let fields: Vec<&str> = s.split_whitespace().collect();
+    if fields.len() < 6 {
+        return None;
+    }
+
+    Some(NodeReasonData {
+        cluster: fields[0].to_string(),
+        node: fields[1].to_string(),
+        state: fields[2].to_string(),
+        // "Unknown" if SLURM didn't record the time
+        timestamp: slurm::parse_timestamp(fields[3]),
+        user: fields[4].to_string(),
+        reason: fields[5..].join(" "),
+    })
+}
@@ -0,0 +1,123 @@
+use crate::command;
+use crate::exporter;
+use crate::slurm;
+
+use log::debug;
+use std::error::Error;
+
+pub struct ReservationData {
+    pub cluster: String,
+    pub name: String,
+    pub nodes: Option<i64>,
+    pub cores: Option<i64>,
+    pub active: bool,
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub flags: Vec<String>,
+}
+
+pub fn update_reservation_metrics(slurm_cluster: &str) -> Result<(), Box<dyn Error>> {
+    let collected = slurm::collect_clusters(slurm_cluster, |cluster| {
+        let stdout = command::run_command(
+            "scontrol",
+            &[
+                "--oneliner".to_string(),
+                format!("--clusters={}", cluster),
+                "show".to_string(),
+                "reservation".to_string(),
+            ],
+        )?;
+        let mut result: Vec<ReservationData> = Vec::new();
+        for line in stdout.lines() {
+            debug!(
+                "reservations.rs:update_reservation_metrics: Processing line: {}",
+                line
+            );
+            let kv = slurm::split_key_value_line(line);
+            // skips "No reservations in the system" and cluster headers
+            let name = match kv.get("ReservationName") {
+                Some(v) => v,
+                None => continue,
+            };
+
+            let flags = match kv.get("Flags") {
+                Some(v) if v != "(null)" => v.split(',').map(|f| f.to_string()).collect(),
+                _ => Vec::new(),
+            };
+            result.push(ReservationData {
+                cluster: cluster.to_string(),
+                name: name.to_string(),
+                nodes: kv.get("NodeCnt").and_then(|v| v.parse::<i64>().ok()),
+                cores: kv.get("CoreCnt").and_then(|v| v.parse::<i64>().ok()),
+                active: kv.get("State").map(|v| v == "ACTIVE").unwrap_or(false),
+                start: kv.get("StartTime").and_then(|v| slurm::parse_timestamp(v)),
+                end: kv.get("EndTime").and_then(|v| slurm::parse_timestamp(v)),
+                flags,
+            });
+        }
+        Ok(result)
+    })?;
+    // clusters without reservations report their maintenance state as well
+    let clusters: Vec<String> = collected.iter().map(|(c, _)| c.clone()).collect();
+    let reservations: Vec<ReservationData> = collected.into_iter().flat_map(|(_, v)| v).collect();
+
+    let now = chrono::Local::now().timestamp();
+
+    exporter::RESERVATION_NODES.reset();
+    exporter::RESERVATION_CORES.reset();
+    exporter::RESERVATION_ACTIVE.reset();
+    exporter::RESERVATION_SECONDS_UNTIL_START.reset();
+    exporter::RESERVATION_SECONDS_UNTIL_END.reset();
+    exporter::RESERVATION_FLAGS.reset();
+    exporter::MAINTENANCE_ACTIVE.reset();
+
+    for cluster in clusters.iter() {
+        let maintenance = reservations
+            .iter()
+            .any(|r| &r.cluster == cluster && r.active && r.flags.iter().any(|f| f == "MAINT"));
+        debug!(
+            "reservations.rs:update_reservation_metrics: Setting MAINTENANCE_ACTIVE {} -> {}",
+            cluster, maintenance
+        );
+        exporter::MAINTENANCE_ACTIVE
+            .with_label_values(&[cluster])
+            .set(maintenance as i64);
+    }
+
+    for r in reservations.iter() {
+        debug!(
+            "reservations.rs:update_reservation_metrics: Setting reservation metrics for {} {}",
+            r.cluster, r.name
+        );
+        let labels = [r.cluster.as_str(), r.name.as_str()];
+        if let Some(v) = r.nodes {
+            exporter::RESERVATION_NODES
+                .with_label_values(&labels)
+                .set(v);
+        }
+        if let Some(v) = r.cores {
+            exporter::RESERVATION_CORES
+                .with_label_values(&labels)
+                .set(v);
+        }
+        exporter::RESERVATION_ACTIVE
+            .with_label_values(&labels)
+            .set(r.active as i64);
+        if let Some(v) = r.start {
+            exporter::RESERVATION_SECONDS_UNTIL_START
+                .with_label_values(&labels)
+                .set(v - now);
+        }
+        if let Some(v) = r.end {
+            exporter::RESERVATION_SECONDS_UNTIL_END
+                .with_label_values(&labels)
+                .set(v - now);
+        }
+        for flag in r.flags.iter() {
+            exporter::RESERVATION_FLAGS
+                .with_label_values(&[&r.cluster, &r.name, flag])
+                .set(1);
+        }
+    }
+    Ok(())
+}
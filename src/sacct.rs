@@ -0,0 +1,231 @@
+use crate::command;
+use crate::constants;
+use crate::exporter;
+use crate::slurm;
+
+use chrono::TimeZone;
+use lazy_static::lazy_static;
+use log::debug;
+use std::error::Error;
+use std::sync::Mutex;
+
+lazy_static! {
+    // End of the time window of the last sacct query, jobs ending after this
+    // timestamp haven't been accounted for yet
+    static ref HIGH_WATER_MARK: Mutex<Option<i64>> = Mutex::new(None);
+}
+
+pub struct FinishedJobData {
+    pub cluster: String,
+    pub partition: String,
+    pub state: String,
+    pub exit_class: String,
+    pub cpu_seconds: i64,
+    pub end: Option<i64>,
+}
+
+// The query interval is the refresh interval of the accounting collector, see
+// Configuration::collector_intervals
+pub fn update_accounting_metrics(slurm_cluster: &str) -> Result<(), Box<dyn Error>> {
+    let mut high_water_mark = match HIGH_WATER_MARK.lock() {
+        Ok(v) => v,
+        Err(e) => e.into_inner(),
+    };
+    let now = chrono::Local::now().timestamp();
+    // Jobs which ended shortly before the query may not have reached slurmdbd yet, they
+    // are counted by the next query
+    let end = now - constants::ACCOUNTING_DELAY;
+
+    let start = match *high_water_mark {
+        Some(v) => v,
+        None => {
+            // Only jobs finishing after the start of the exporter are counted
+            debug!(
+                "sacct.rs:update_accounting_metrics: Initialising high water mark to {}",
+                end
+            );
+            *high_water_mark = Some(end);
+            return Ok(());
+        }
+    };
+
+    let stdout = command::run_command(
+        "sacct",
+        &[
+            "--noheader".to_string(),
+            "--parsable2".to_string(),
+            "--allusers".to_string(),
+            "--allocations".to_string(),
+            format!("--clusters={}", slurm_cluster),
+            format!("--state={}", FINAL_STATES.join(",")),
+            format!("--starttime={}", format_timestamp(start)),
+            format!("--endtime={}", format_timestamp(end)),
+            "--format=Cluster,Partition,State,ExitCode,CPUTimeRAW,End".to_string(),
+        ],
+    )?;
+
+    update_accounting_metrics_from_output(&stdout, start, end)?;
+
+    *high_water_mark = Some(end);
+    Ok(())
+}
+
+// Count the finished jobs of sacct output which ended within the time window (start, end]
+fn update_accounting_metrics_from_output(
+    stdout: &str,
+    start: i64,
+    end: i64,
+) -> Result<(), Box<dyn Error>> {
+    for job in parse_sacct_data(stdout)? {
+        // sacct also reports jobs that were in one of the requested states at some point
+        // during the time window, only count jobs which ended within the window
+        match job.end {
+            Some(v) if v > start && v <= end => {}
+            _ => continue,
+        };
+        debug!(
+            "sacct.rs:update_accounting_metrics_from_output: Counting finished job {} {} {} {} -> {} CPU seconds",
+            job.cluster, job.partition, job.state, job.exit_class, job.cpu_seconds
+        );
+        exporter::JOBS_FINISHED
+            .with_label_values(&[&job.cluster, &job.partition, &job.state, &job.exit_class])
+            .inc();
+        exporter::JOBS_FINISHED_CPU_SECONDS
+            .with_label_values(&[&job.cluster, &job.partition, &job.state])
+            .inc_by(job.cpu_seconds as u64);
+    }
+    Ok(())
+}
+
+fn parse_sacct_data(stdout: &str) -> Result<Vec<FinishedJobData>, Box<dyn Error>> {
+    let mut result: Vec<FinishedJobData> = Vec::new();
+
+    for line in stdout.lines() {
+        debug!("sacct.rs:parse_sacct_data: Processing line: {}", line);
+        let job = match split_sacct_line(line) {
+            Some(v) => v,
+            None => bail!("Can't extract finished job data from output '{}'", line),
+        };
+        result.push(job);
+    }
+    Ok(result)
+}
+
+const FINAL_STATES: &[&str] = &[
+    "BOOT_FAIL",
+    "CANCELLED",
+    "COMPLETED",
+    "DEADLINE",
+    "FAILED",
+    "NODE_FAIL",
+    "OUT_OF_MEMORY",
+    "PREEMPTED",
+    "TIMEOUT",
+];
+
+fn split_sacct_line(s: &str) -> Option<FinishedJobData> {
+    let fields: Vec<&str> = s.split('|').collect();
+    if fields.len() != 6 {
+        return None;
+    }
+
+    // States can contain additional information, e.g. "CANCELLED by 1234"
+    let state = fields[2].split_whitespace().next()?;
+
+    Some(FinishedJobData {
+        cluster: fields[0].to_string(),
+        partition: fields[1].to_string(),
+        state: state.to_string(),
+        exit_class: exit_code_class(fields[3]).to_string(),
+        cpu_seconds: fields[4].parse::<i64>().unwrap_or(0),
+        end: slurm::parse_timestamp(fields[5]),
+    })
+}
+
+// ExitCode is reported as <exit code>:<signal>
+fn exit_code_class(s: &str) -> &str {
+    match s.split_once(':') {
+        Some(("0", "0")) => "success",
+        Some((_, "0")) => "error",
+        Some(_) => "signal",
+        None => "unknown",
+    }
+}
+
+fn format_timestamp(t: i64) -> String {
+    match chrono::Local.timestamp_opt(t, 0).earliest() {
+        Some(v) => v.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        None => "now".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_sacct_line() {
+        let job =
+            split_sacct_line("c1|batch|CANCELLED by 1234|0:15|3600|2022-06-13T08:15:42").unwrap();
+        assert_eq!(job.cluster, "c1");
+        assert_eq!(job.partition, "batch");
+        assert_eq!(job.state, "CANCELLED");
+        assert_eq!(job.exit_class, "signal");
+        assert_eq!(job.cpu_seconds, 3600);
+        assert_eq!(job.end, slurm::parse_timestamp("2022-06-13T08:15:42"));
+
+        let job = split_sacct_line("c1|batch|COMPLETED|0:0||Unknown").unwrap();
+        assert_eq!(job.cpu_seconds, 0);
+        assert_eq!(job.end, None);
+
+        assert!(split_sacct_line("c1|batch|COMPLETED|0:0|3600").is_none());
+    }
+
+    #[test]
+    fn test_exit_code_class() {
+        assert_eq!(exit_code_class("0:0"), "success");
+        assert_eq!(exit_code_class("1:0"), "error");
+        assert_eq!(exit_code_class("0:9"), "signal");
+        assert_eq!(exit_code_class("CANCELLED by 1234"), "unknown");
+    }
+
+    #[test]
+    fn test_only_jobs_ending_within_window_are_counted() {
+        let now = chrono::Local::now().timestamp();
+        let end = now - constants::ACCOUNTING_DELAY;
+        let start = end - 300;
+        let line = |partition: &str, t: i64| {
+            format!(
+                "window|{}|COMPLETED|0:0|60|{}",
+                partition,
+                format_timestamp(t)
+            )
+        };
+        let output = [
+            // counted by the previous query
+            line("before", start),
+            line("within", start + 1),
+            line("within", end),
+            // may not have reached slurmdbd yet, counted by the next query
+            line("delayed", end + 1),
+            line("delayed", now),
+        ]
+        .join("\n");
+
+        update_accounting_metrics_from_output(&output, start, end).unwrap();
+        let finished = |partition: &str| {
+            exporter::JOBS_FINISHED
+                .with_label_values(&["window", partition, "COMPLETED", "success"])
+                .get()
+        };
+        assert_eq!(finished("before"), 0);
+        assert_eq!(finished("within"), 2);
+        assert_eq!(finished("delayed"), 0);
+        assert_eq!(
+            exporter::JOBS_FINISHED_CPU_SECONDS
+                .with_label_values(&["window", "within", "COMPLETED"])
+                .get(),
+            120
+        );
+    }
+}
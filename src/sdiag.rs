@@ -0,0 +1,305 @@
+use crate::command;
+use crate::exporter;
+use crate::slurm;
+
+use log::debug;
+use std::collections::HashMap;
+use std::error::Error;
+
+#[derive(Default)]
+pub struct SdiagData {
+    // Key/value pairs of the general, main scheduler and backfill scheduler sections,
+    // e.g. "Server thread count" -> 3
+    pub general: HashMap<String, i64>,
+    pub main: HashMap<String, i64>,
+    pub backfill: HashMap<String, i64>,
+    pub rpc_by_type: Vec<RpcStatistics>,
+    pub rpc_by_user: Vec<RpcStatistics>,
+}
+
+pub struct RpcStatistics {
+    pub name: String,
+    pub count: i64,
+    // in microseconds
+    pub average_time: i64,
+    pub total_time: i64,
+}
+
+enum SdiagSection {
+    General,
+    Main,
+    Backfill,
+    RpcByType,
+    RpcByUser,
+    Other,
+}
+
+pub fn update_sdiag_metrics(slurm_cluster: &str) -> Result<(), Box<dyn Error>> {
+    let result = slurm::collect_clusters(slurm_cluster, |cluster| {
+        let stdout = command::run_command("sdiag", &[format!("--cluster={}", cluster)])?;
+        Ok(parse_sdiag(&stdout))
+    })?;
+
+    exporter::SCHEDULER_SERVER_THREADS.reset();
+    exporter::SCHEDULER_AGENT_QUEUE_SIZE.reset();
+    exporter::SCHEDULER_AGENT_COUNT.reset();
+    exporter::SCHEDULER_AGENT_THREADS.reset();
+    exporter::SCHEDULER_DBD_AGENT_QUEUE_SIZE.reset();
+    exporter::SCHEDULER_CYCLE_LAST.reset();
+    exporter::SCHEDULER_CYCLE_MAX.reset();
+    exporter::SCHEDULER_CYCLE_MEAN.reset();
+    exporter::SCHEDULER_DEPTH_MEAN.reset();
+    exporter::SCHEDULER_BACKFILL_DEPTH_LAST.reset();
+    exporter::SCHEDULER_BACKFILLED_JOBS.reset();
+    exporter::SCHEDULER_BACKFILLED_JOBS_SINCE_CYCLE.reset();
+    exporter::RPC_TYPE_COUNT.reset();
+    exporter::RPC_TYPE_AVG_TIME.reset();
+    exporter::RPC_TYPE_TOTAL_TIME.reset();
+    exporter::RPC_USER_COUNT.reset();
+    exporter::RPC_USER_AVG_TIME.reset();
+    exporter::RPC_USER_TOTAL_TIME.reset();
+
+    for (clu, data) in result.iter() {
+        debug!(
+            "sdiag.rs:update_sdiag_metrics: Setting scheduler statistics for cluster {}",
+            clu
+        );
+        if let Some(v) = data.general.get("Server thread count") {
+            exporter::SCHEDULER_SERVER_THREADS
+                .with_label_values(&[clu])
+                .set(*v);
+        }
+        if let Some(v) = data.general.get("Agent queue size") {
+            exporter::SCHEDULER_AGENT_QUEUE_SIZE
+                .with_label_values(&[clu])
+                .set(*v);
+        }
+        if let Some(v) = data.general.get("Agent count") {
+            exporter::SCHEDULER_AGENT_COUNT
+                .with_label_values(&[clu])
+                .set(*v);
+        }
+        if let Some(v) = data.general.get("Agent thread count") {
+            exporter::SCHEDULER_AGENT_THREADS
+                .with_label_values(&[clu])
+                .set(*v);
+        }
+        if let Some(v) = data.general.get("DBD Agent queue size") {
+            exporter::SCHEDULER_DBD_AGENT_QUEUE_SIZE
+                .with_label_values(&[clu])
+                .set(*v);
+        }
+
+        for (scheduler, stats, depth_mean) in [
+            ("main", &data.main, "Mean depth cycle"),
+            ("backfill", &data.backfill, "Depth Mean"),
+        ] {
+            // cycle times are reported in microseconds
+            if let Some(v) = stats.get("Last cycle") {
+                exporter::SCHEDULER_CYCLE_LAST
+                    .with_label_values(&[clu, scheduler])
+                    .set(*v as f64 / 1e+06);
+            }
+            if let Some(v) = stats.get("Max cycle") {
+                exporter::SCHEDULER_CYCLE_MAX
+                    .with_label_values(&[clu, scheduler])
+                    .set(*v as f64 / 1e+06);
+            }
+            if let Some(v) = stats.get("Mean cycle") {
+                exporter::SCHEDULER_CYCLE_MEAN
+                    .with_label_values(&[clu, scheduler])
+                    .set(*v as f64 / 1e+06);
+            }
+            if let Some(v) = stats.get(depth_mean) {
+                exporter::SCHEDULER_DEPTH_MEAN
+                    .with_label_values(&[clu, scheduler])
+                    .set(*v);
+            }
+        }
+
+        if let Some(v) = data.backfill.get("Last depth cycle") {
+            exporter::SCHEDULER_BACKFILL_DEPTH_LAST
+                .with_label_values(&[clu])
+                .set(*v);
+        }
+        if let Some(v) = data
+            .backfill
+            .get("Total backfilled jobs (since last slurm start)")
+        {
+            exporter::SCHEDULER_BACKFILLED_JOBS
+                .with_label_values(&[clu])
+                .set(*v);
+        }
+        if let Some(v) = data
+            .backfill
+            .get("Total backfilled jobs (since last stats cycle start)")
+        {
+            exporter::SCHEDULER_BACKFILLED_JOBS_SINCE_CYCLE
+                .with_label_values(&[clu])
+                .set(*v);
+        }
+
+        for rpc in data.rpc_by_type.iter() {
+            exporter::RPC_TYPE_COUNT
+                .with_label_values(&[clu, &rpc.name])
+                .set(rpc.count);
+            exporter::RPC_TYPE_AVG_TIME
+                .with_label_values(&[clu, &rpc.name])
+                .set(rpc.average_time as f64 / 1e+06);
+            exporter::RPC_TYPE_TOTAL_TIME
+                .with_label_values(&[clu, &rpc.name])
+                .set(rpc.total_time as f64 / 1e+06);
+        }
+        for rpc in data.rpc_by_user.iter() {
+            exporter::RPC_USER_COUNT
+                .with_label_values(&[clu, &rpc.name])
+                .set(rpc.count);
+            exporter::RPC_USER_AVG_TIME
+                .with_label_values(&[clu, &rpc.name])
+                .set(rpc.average_time as f64 / 1e+06);
+            exporter::RPC_USER_TOTAL_TIME
+                .with_label_values(&[clu, &rpc.name])
+                .set(rpc.total_time as f64 / 1e+06);
+        }
+    }
+    Ok(())
+}
+
+fn parse_sdiag(s: &str) -> SdiagData {
+    let mut result = SdiagData::default();
+    let mut section = SdiagSection::General;
+
+    for line in s.lines() {
+        debug!("sdiag.rs:parse_sdiag: Processing line: {}", line);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('*') {
+            continue;
+        }
+
+        if line.starts_with("Main schedule statistics") {
+            section = SdiagSection::Main;
+            continue;
+        } else if line.starts_with("Backfilling stats") {
+            section = SdiagSection::Backfill;
+            continue;
+        } else if line.starts_with("Remote Procedure Call statistics by message type") {
+            section = SdiagSection::RpcByType;
+            continue;
+        } else if line.starts_with("Remote Procedure Call statistics by user") {
+            section = SdiagSection::RpcByUser;
+            continue;
+        } else if line.starts_with("Pending RPC statistics") || line.starts_with("Latency for") {
+            section = SdiagSection::Other;
+            continue;
+        }
+
+        match section {
+            SdiagSection::General => {
+                if let Some((k, v)) = split_sdiag_value(line) {
+                    result.general.insert(k, v);
+                }
+            }
+            SdiagSection::Main => {
+                if let Some((k, v)) = split_sdiag_value(line) {
+                    result.main.insert(k, v);
+                }
+            }
+            SdiagSection::Backfill => {
+                if let Some((k, v)) = split_sdiag_value(line) {
+                    result.backfill.insert(k, v);
+                }
+            }
+            SdiagSection::RpcByType => {
+                if let Some(v) = split_rpc_line(line) {
+                    result.rpc_by_type.push(v);
+                }
+            }
+            SdiagSection::RpcByUser => {
+                if let Some(v) = split_rpc_line(line) {
+                    result.rpc_by_user.push(v);
+                }
+            }
+            SdiagSection::Other => {}
+        }
+    }
+    result
+}
+
+// Lines like "Server thread count:  3", values which are not plain numbers
+// (e.g. timestamps) are skipped
+fn split_sdiag_value(s: &str) -> Option<(String, i64)> {
+    let (key, value) = s.rsplit_once(':')?;
+    let value = value.trim().parse::<i64>().ok()?;
+    Some((key.trim().to_string(), value))
+}
+
+// Lines like "REQUEST_PARTITION_INFO  ( 2009) count:1234 ave_time:150 total_time:185100"
+// or "root  (       0) count:2000 ave_time:200 total_time:400000"
+fn split_rpc_line(s: &str) -> Option<RpcStatistics> {
+    let mut fields = s.split_whitespace();
+    let name = fields.next()?;
+    let mut count: Option<i64> = None;
+    let mut average_time: Option<i64> = None;
+    let mut total_time: Option<i64> = None;
+
+    for field in fields {
+        if let Some(v) = field.strip_prefix("count:") {
+            count = v.parse::<i64>().ok();
+        } else if let Some(v) = field.strip_prefix("ave_time:") {
+            average_time = v.parse::<i64>().ok();
+        } else if let Some(v) = field.strip_prefix("total_time:") {
+            total_time = v.parse::<i64>().ok();
+        }
+    }
+
+    Some(RpcStatistics {
+        name: name.to_string(),
+        count: count?,
+        average_time: average_time?,
+        total_time: total_time?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sdiag() {
+        let data = parse_sdiag(include_str!("../tests/fixtures/sdiag.txt"));
+
+        assert_eq!(data.general.get("Server thread count"), Some(&3));
+        assert_eq!(data.general.get("DBD Agent queue size"), Some(&0));
+        assert_eq!(data.main.get("Last cycle"), Some(&1234));
+        assert_eq!(data.main.get("Mean depth cycle"), Some(&50));
+        assert_eq!(data.backfill.get("Last cycle"), Some(&300000));
+        assert_eq!(data.backfill.get("Depth Mean"), Some(&25));
+        assert_eq!(
+            data.backfill
+                .get("Total backfilled jobs (since last slurm start)"),
+            Some(&500)
+        );
+        // timestamps are not exported
+        assert_eq!(data.backfill.get("Last cycle when"), None);
+
+        assert_eq!(data.rpc_by_type.len(), 2);
+        assert_eq!(data.rpc_by_type[0].name, "REQUEST_PARTITION_INFO");
+        assert_eq!(data.rpc_by_type[0].count, 1234);
+        assert_eq!(data.rpc_by_type[0].average_time, 150);
+        assert_eq!(data.rpc_by_type[0].total_time, 185100);
+
+        assert_eq!(data.rpc_by_user.len(), 2);
+        assert_eq!(data.rpc_by_user[1].name, "slurm");
+        assert_eq!(data.rpc_by_user[1].count, 100);
+        assert_eq!(data.rpc_by_user[1].total_time, 10000);
+    }
+
+    #[test]
+    fn test_split_rpc_line() {
+        assert!(split_rpc_line("No pending RPCs").is_none());
+        let rpc =
+            split_rpc_line("root  (       0) count:2000 ave_time:200 total_time:400000").unwrap();
+        assert_eq!(rpc.name, "root");
+        assert_eq!(rpc.average_time, 200);
+    }
+}
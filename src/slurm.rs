@@ -1,166 +1,390 @@
+use crate::command::run_command;
+use crate::config;
 use crate::constants;
 use crate::exporter;
 
-use log::debug;
-use std::collections::HashMap;
-use std::env;
+use chrono::TimeZone;
+use lazy_static::lazy_static;
+use log::{debug, warn};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::process::Command;
+use std::sync::Mutex;
 
-pub fn update_job_metrics(slurm_cluster: &str, bitmask: u8) -> Result<(), Box<dyn Error>> {
-    // <cluster>: {
-    //  <partition>: {
-    //      <state>: <count>
-    //  }
-    // }
-    let mut job_node_states: HashMap<String, HashMap<String, HashMap<String, i64>>> =
-        HashMap::new();
-    let mut job_task_states: HashMap<String, HashMap<String, HashMap<String, i64>>> =
-        HashMap::new();
-    let mut job_cpu_states: HashMap<String, HashMap<String, HashMap<String, i64>>> = HashMap::new();
-    let mut job_count_states: HashMap<String, HashMap<String, HashMap<String, i64>>> =
-        HashMap::new();
+lazy_static! {
+    // UNIX timestamp of the last resolution and the cluster names of "all", the lock is
+    // held during the resolution so concurrent collectors don't resolve the names again
+    static ref CLUSTER_NAMES: Mutex<Option<(i64, Vec<String>)>> = Mutex::new(None);
+}
 
-    env::set_var("LANG", "C");
-    debug!("slurm.rs:update_job_metrics: running external command: squeue --noheader --Format=Cluster,Partition,State,NumNodes,NumTasks,NumCPUs --clusters={} --all", slurm_cluster);
-    let squeue = Command::new("squeue")
-        .arg("--noheader")
-        .arg("--Format=Cluster,Partition,State,NumNodes,NumTasks,NumCPUs")
-        .arg(format!("--clusters={}", slurm_cluster))
-        .arg("--all")
-        .output()?;
+pub struct JobData {
+    pub cluster: String,
+    pub partition: String,
+    pub state: String,
+    pub nodes: i64,
+    pub tasks: i64,
+    pub cpus: i64,
+    pub user: String,
+    pub account: String,
+    pub qos: String,
+    pub submit_time: Option<i64>,
+    pub eligible_time: Option<i64>,
+    pub time_used: Option<i64>,
+    pub time_limit: Option<i64>,
+    pub array_job_id: String,
+    // number of array tasks represented by this entry, 1 for regular jobs
+    pub array_tasks: i64,
+    pub reason: String,
+}
 
-    let rc = match squeue.status.code() {
-        Some(v) => v,
-        None => {
-            bail!("Can't get return code of squeue command");
+impl JobData {
+    // value of a configurable job label dimension, see constants::SUPPORTED_JOB_LABELS
+    pub fn label_value(&self, label: &str) -> &str {
+        match label {
+            "account" => &self.account,
+            "partition" => &self.partition,
+            "qos" => &self.qos,
+            "user" => &self.user,
+            _ => "",
         }
-    };
-    debug!(
-        "slurm.rs:update_job_metrics: external command finished with exit code {}",
-        rc
-    );
-
-    if !squeue.status.success() {
-        bail!("squeue command exited with non-normal exit code {}", rc);
     }
+}
+
+pub fn update_job_metrics(cfg: &config::Configuration) -> Result<(), Box<dyn Error>> {
+    let stdout = run_command(
+        "squeue",
+        &[
+            "--noheader".to_string(),
+            // The default field width of 20 characters truncates long user, account and QOS
+            // names. Reason can contain whitespaces and must be the last field
+            "--Format=Cluster,Partition,State,NumNodes,NumTasks,NumCPUs,UserName:64,Account:64,QOS:64,SubmitTime,EligibleTime,TimeUsed,TimeLimit,ArrayJobID,ArrayTaskID:256,Reason:128"
+                .to_string(),
+            format!("--clusters={}", cfg.clusters),
+            "--all".to_string(),
+        ],
+    )?;
+    update_job_metrics_from_output(cfg, &stdout)
+}
+
+// Build the job metrics from squeue output, series of jobs no longer reported are removed
+fn update_job_metrics_from_output(
+    cfg: &config::Configuration,
+    stdout: &str,
+) -> Result<(), Box<dyn Error>> {
+    let bitmask = cfg.bitmask;
+    // HashMap of
+    //  [<cluster>, <job label>..., <state>]: <count>
+    let mut job_node_states: HashMap<Vec<String>, i64> = HashMap::new();
+    let mut job_task_states: HashMap<Vec<String>, i64> = HashMap::new();
+    let mut job_cpu_states: HashMap<Vec<String>, i64> = HashMap::new();
+    let mut job_count_states: HashMap<Vec<String>, i64> = HashMap::new();
+    // HashMap of
+    //  [<cluster>, <partition>, <reason>]: <count>
+    let mut pending_reason_count: HashMap<Vec<String>, i64> = HashMap::new();
+    let mut pending_reason_cpus: HashMap<Vec<String>, i64> = HashMap::new();
+    let mut pending_reason_nodes: HashMap<Vec<String>, i64> = HashMap::new();
+    // HashMap of
+    //  [<cluster>, <partition>]: [(<wait time>, <number of jobs>), ...]
+    let mut pending_wait: HashMap<Vec<String>, Vec<(i64, i64)>> = HashMap::new();
+    let mut pending_eligible_wait: HashMap<Vec<String>, Vec<(i64, i64)>> = HashMap::new();
+    // HashMap of
+    //  [<cluster>, <partition>]: [(<elapsed time>, <time limit>), ...]
+    let mut running_times: HashMap<Vec<String>, Vec<(i64, Option<i64>)>> = HashMap::new();
+    // HashMap of
+    //  [<cluster>, <partition>]: {<array job id>, ...}
+    let mut array_jobs_pending: HashMap<Vec<String>, HashSet<String>> = HashMap::new();
+    let mut array_tasks_pending: HashMap<Vec<String>, i64> = HashMap::new();
+
+    let now = chrono::Local::now().timestamp();
+    let job_labels = exporter::job_labels();
+
+    for job in parse_job_data(stdout)? {
+        let mut key: Vec<String> = vec![job.cluster.clone()];
+        for label in job_labels.iter() {
+            key.push(job.label_value(label).to_string());
+        }
+        key.push(job.state.clone());
+
+        // pending array tasks are reported as a single entry, every task requests the
+        // resources of the entry
+        let n = job.array_tasks;
 
-    let stdout = String::from_utf8(squeue.stdout)?;
-    for line in stdout.lines() {
-        debug!("slurm.rs:update_job_metrics: Processing line: {}", line);
-        let (c, p, s, nodes, tasks, cpus) = match split_job_state_line(line) {
-            Some(v) => v,
-            None =>  bail!(
-                "Can't extract tuple cluster, partition, state, nodes, tasks and cpus from output '{}'",
-                line
-            ),
-        };
         if bitmask & constants::BITMASK_JOB_NODES == constants::BITMASK_JOB_NODES {
-            let cluster = job_node_states
-                .entry(c.clone())
-                .or_insert_with(HashMap::<String, HashMap<String, i64>>::new);
-            let partition = cluster
-                .entry(p.clone())
-                .or_insert_with(HashMap::<String, i64>::new);
-            *partition.entry(s.clone()).or_insert(0) += nodes;
+            *job_node_states.entry(key.clone()).or_insert(0) += n * job.nodes;
         }
 
         if bitmask & constants::BITMASK_JOB_TASKS == constants::BITMASK_JOB_TASKS {
-            let cluster = job_task_states
-                .entry(c.clone())
-                .or_insert_with(HashMap::<String, HashMap<String, i64>>::new);
-            let partition = cluster
-                .entry(p.clone())
-                .or_insert_with(HashMap::<String, i64>::new);
-            *partition.entry(s.clone()).or_insert(0) += tasks;
+            *job_task_states.entry(key.clone()).or_insert(0) += n * job.tasks;
         }
 
         if bitmask & constants::BITMASK_JOB_CPUS == constants::BITMASK_JOB_CPUS {
-            let cluster = job_cpu_states
-                .entry(c.clone())
-                .or_insert_with(HashMap::<String, HashMap<String, i64>>::new);
-            let partition = cluster
-                .entry(p.clone())
-                .or_insert_with(HashMap::<String, i64>::new);
-            *partition.entry(s.clone()).or_insert(0) += cpus;
+            *job_cpu_states.entry(key.clone()).or_insert(0) += n * job.cpus;
         }
 
         if bitmask & constants::BITMASK_JOB_COUNT == constants::BITMASK_JOB_COUNT {
-            let cluster = job_count_states
-                .entry(c)
-                .or_insert_with(HashMap::<String, HashMap<String, i64>>::new);
-            let partition = cluster.entry(p).or_insert_with(HashMap::<String, i64>::new);
-            *partition.entry(s).or_insert(0) += 1;
+            *job_count_states.entry(key).or_insert(0) += n;
+
+            if job.state == "PENDING" && job.array_job_id != "N/A" {
+                let key = vec![job.cluster.clone(), job.partition.clone()];
+                array_jobs_pending
+                    .entry(key.clone())
+                    .or_default()
+                    .insert(job.array_job_id.clone());
+                *array_tasks_pending.entry(key).or_insert(0) += n;
+            }
         }
-    }
 
-    if bitmask & constants::BITMASK_JOB_NODES == constants::BITMASK_JOB_NODES {
-        for (clu, cpart) in job_node_states.iter() {
-            for (part, pstate) in cpart.iter() {
-                for (state, count) in pstate.iter() {
-                    debug!(
-                        "slurm.rs:update_job_metrics: Setting JOBS_NODES {} {} {} -> {}",
-                        clu, part, state, *count
-                    );
-                    exporter::JOBS_NODES
-                        .with_label_values(&[clu, part, state])
-                        .set(*count);
+        if bitmask & constants::BITMASK_PENDING_REASONS == constants::BITMASK_PENDING_REASONS
+            && job.state == "PENDING"
+        {
+            let key = vec![
+                job.cluster.clone(),
+                job.partition.clone(),
+                normalise_pending_reason(&job.reason),
+            ];
+            *pending_reason_count.entry(key.clone()).or_insert(0) += n;
+            *pending_reason_cpus.entry(key.clone()).or_insert(0) += n * job.cpus;
+            *pending_reason_nodes.entry(key).or_insert(0) += n * job.nodes;
+        }
+
+        if bitmask & constants::BITMASK_PENDING_WAIT == constants::BITMASK_PENDING_WAIT
+            && job.state == "PENDING"
+        {
+            let key = vec![job.cluster.clone(), job.partition.clone()];
+            if let Some(submit) = job.submit_time {
+                pending_wait
+                    .entry(key.clone())
+                    .or_default()
+                    .push(((now - submit).max(0), n));
+            }
+            // eligible time is in the future for jobs with a begin time or dependency
+            if let Some(eligible) = job.eligible_time {
+                if eligible <= now {
+                    pending_eligible_wait
+                        .entry(key)
+                        .or_default()
+                        .push((now - eligible, n));
                 }
             }
         }
+
+        if bitmask & constants::BITMASK_JOB_RUNTIME == constants::BITMASK_JOB_RUNTIME
+            && job.state == "RUNNING"
+        {
+            if let Some(used) = job.time_used {
+                running_times
+                    .entry(vec![job.cluster.clone(), job.partition.clone()])
+                    .or_default()
+                    .push((used, job.time_limit));
+            }
+        }
+    }
+
+    if bitmask & constants::BITMASK_JOB_NODES == constants::BITMASK_JOB_NODES {
+        exporter::JOBS_NODES.reset();
+        for (labels, count) in job_node_states.iter() {
+            debug!(
+                "slurm.rs:update_job_metrics_from_output: Setting JOBS_NODES {} -> {}",
+                labels.join(" "),
+                *count
+            );
+            exporter::JOBS_NODES
+                .with_label_values(&label_refs(labels))
+                .set(*count);
+        }
     }
 
     if bitmask & constants::BITMASK_JOB_TASKS == constants::BITMASK_JOB_TASKS {
-        for (clu, cpart) in job_task_states.iter() {
-            for (part, pstate) in cpart.iter() {
-                for (state, count) in pstate.iter() {
-                    debug!(
-                        "slurm.rs:update_job_metrics: Setting JOBS_TASKS {} {} {} -> {}",
-                        clu, part, state, *count
-                    );
-                    exporter::JOBS_TASKS
-                        .with_label_values(&[clu, part, state])
-                        .set(*count);
-                }
-            }
+        exporter::JOBS_TASKS.reset();
+        for (labels, count) in job_task_states.iter() {
+            debug!(
+                "slurm.rs:update_job_metrics_from_output: Setting JOBS_TASKS {} -> {}",
+                labels.join(" "),
+                *count
+            );
+            exporter::JOBS_TASKS
+                .with_label_values(&label_refs(labels))
+                .set(*count);
         }
     }
 
     if bitmask & constants::BITMASK_JOB_CPUS == constants::BITMASK_JOB_CPUS {
-        for (clu, cpart) in job_cpu_states.iter() {
-            for (part, pstate) in cpart.iter() {
-                for (state, count) in pstate.iter() {
-                    debug!(
-                        "slurm.rs:update_job_metrics: Setting JOBS_CPUS {} {} {} -> {}",
-                        clu, part, state, *count
-                    );
-                    exporter::JOBS_CPUS
-                        .with_label_values(&[clu, part, state])
-                        .set(*count);
+        exporter::JOBS_CPUS.reset();
+        for (labels, count) in job_cpu_states.iter() {
+            debug!(
+                "slurm.rs:update_job_metrics_from_output: Setting JOBS_CPUS {} -> {}",
+                labels.join(" "),
+                *count
+            );
+            exporter::JOBS_CPUS
+                .with_label_values(&label_refs(labels))
+                .set(*count);
+        }
+    }
+
+    if bitmask & constants::BITMASK_JOB_COUNT == constants::BITMASK_JOB_COUNT {
+        exporter::JOBS_COUNT.reset();
+        for (labels, count) in job_count_states.iter() {
+            debug!(
+                "slurm.rs:update_job_metrics_from_output: Setting JOBS_COUNT {} -> {}",
+                labels.join(" "),
+                *count
+            );
+            exporter::JOBS_COUNT
+                .with_label_values(&label_refs(labels))
+                .set(*count);
+        }
+
+        exporter::JOBS_ARRAY_JOBS_PENDING.reset();
+        exporter::JOBS_ARRAY_TASKS_PENDING.reset();
+        for (labels, ids) in array_jobs_pending.iter() {
+            let tasks = array_tasks_pending.get(labels).copied().unwrap_or(0);
+            debug!(
+                "slurm.rs:update_job_metrics_from_output: Setting JOBS_ARRAY_*_PENDING {} -> {}/{}",
+                labels.join(" "),
+                ids.len(),
+                tasks
+            );
+            exporter::JOBS_ARRAY_JOBS_PENDING
+                .with_label_values(&label_refs(labels))
+                .set(ids.len() as i64);
+            exporter::JOBS_ARRAY_TASKS_PENDING
+                .with_label_values(&label_refs(labels))
+                .set(tasks);
+        }
+    }
+
+    if bitmask & constants::BITMASK_PENDING_REASONS == constants::BITMASK_PENDING_REASONS {
+        exporter::JOBS_PENDING_COUNT.reset();
+        exporter::JOBS_PENDING_CPUS.reset();
+        exporter::JOBS_PENDING_NODES.reset();
+        for (labels, count) in pending_reason_count.iter() {
+            let cpus = pending_reason_cpus.get(labels).copied().unwrap_or(0);
+            let nodes = pending_reason_nodes.get(labels).copied().unwrap_or(0);
+            debug!(
+                "slurm.rs:update_job_metrics_from_output: Setting JOBS_PENDING_* {} -> {}/{}/{}",
+                labels.join(" "),
+                *count,
+                cpus,
+                nodes
+            );
+            exporter::JOBS_PENDING_COUNT
+                .with_label_values(&label_refs(labels))
+                .set(*count);
+            exporter::JOBS_PENDING_CPUS
+                .with_label_values(&label_refs(labels))
+                .set(cpus);
+            exporter::JOBS_PENDING_NODES
+                .with_label_values(&label_refs(labels))
+                .set(nodes);
+        }
+    }
+
+    if bitmask & constants::BITMASK_PENDING_WAIT == constants::BITMASK_PENDING_WAIT {
+        exporter::JOBS_PENDING_WAIT.reset();
+        exporter::JOBS_PENDING_ELIGIBLE_WAIT.reset();
+        exporter::JOBS_PENDING_OLDEST_ELIGIBLE.reset();
+        for (labels, waits) in pending_wait.iter() {
+            debug!(
+                "slurm.rs:update_job_metrics_from_output: Setting JOBS_PENDING_WAIT {} -> {} entries",
+                labels.join(" "),
+                waits.len()
+            );
+            let histogram = exporter::JOBS_PENDING_WAIT.with_label_values(&label_refs(labels));
+            for (wait, count) in waits.iter() {
+                for _ in 0..*count {
+                    histogram.observe(*wait as f64);
                 }
             }
         }
+        for (labels, waits) in pending_eligible_wait.iter() {
+            let oldest = waits.iter().map(|(w, _)| *w).max().unwrap_or(0);
+            debug!(
+                "slurm.rs:update_job_metrics_from_output: Setting JOBS_PENDING_ELIGIBLE_WAIT {} -> {} entries, oldest {}",
+                labels.join(" "),
+                waits.len(),
+                oldest
+            );
+            let histogram =
+                exporter::JOBS_PENDING_ELIGIBLE_WAIT.with_label_values(&label_refs(labels));
+            for (wait, count) in waits.iter() {
+                for _ in 0..*count {
+                    histogram.observe(*wait as f64);
+                }
+            }
+            exporter::JOBS_PENDING_OLDEST_ELIGIBLE
+                .with_label_values(&label_refs(labels))
+                .set(oldest);
+        }
     }
 
-    if bitmask & constants::BITMASK_JOB_COUNT == constants::BITMASK_JOB_COUNT {
-        for (clu, cpart) in job_count_states.iter() {
-            for (part, pstate) in cpart.iter() {
-                for (state, count) in pstate.iter() {
-                    debug!(
-                        "slurm.rs:update_job_metrics: Setting JOBS_COUNT {} {} {} -> {}",
-                        clu, part, state, *count
-                    );
-                    exporter::JOBS_COUNT
-                        .with_label_values(&[clu, part, state])
-                        .set(*count);
+    if bitmask & constants::BITMASK_JOB_RUNTIME == constants::BITMASK_JOB_RUNTIME {
+        exporter::JOBS_RUNNING_ELAPSED.reset();
+        exporter::JOBS_RUNNING_TIME_LIMIT_RATIO.reset();
+        exporter::JOBS_RUNNING_NEAR_TIME_LIMIT.reset();
+        for (labels, times) in running_times.iter() {
+            let mut near_limit: i64 = 0;
+            let elapsed = exporter::JOBS_RUNNING_ELAPSED.with_label_values(&label_refs(labels));
+            let ratio =
+                exporter::JOBS_RUNNING_TIME_LIMIT_RATIO.with_label_values(&label_refs(labels));
+            for (used, limit) in times.iter() {
+                elapsed.observe(*used as f64);
+                // jobs without time limit (UNLIMITED) can't run into their time limit
+                if let Some(limit) = limit {
+                    if *limit > 0 {
+                        ratio.observe(*used as f64 / *limit as f64);
+                    }
+                    if limit - used <= cfg.time_limit_threshold {
+                        near_limit += 1;
+                    }
                 }
             }
+            debug!(
+                "slurm.rs:update_job_metrics_from_output: Setting JOBS_RUNNING_* {} -> {} jobs, {} near time limit",
+                labels.join(" "),
+                times.len(),
+                near_limit
+            );
+            exporter::JOBS_RUNNING_NEAR_TIME_LIMIT
+                .with_label_values(&label_refs(labels))
+                .set(near_limit);
         }
     }
     Ok(())
 }
 
+fn parse_job_data(stdout: &str) -> Result<Vec<JobData>, Box<dyn Error>> {
+    let mut result: Vec<JobData> = Vec::new();
+
+    for line in stdout.lines() {
+        debug!("slurm.rs:parse_job_data: Processing line: {}", line);
+        let job = match split_job_state_line(line) {
+            Some(v) => v,
+            None => bail!("Can't extract job data from output '{}'", line),
+        };
+        result.push(job);
+    }
+    Ok(result)
+}
+
+pub fn label_refs(labels: &[String]) -> Vec<&str> {
+    labels.iter().map(|v| v.as_str()).collect()
+}
+
 pub fn update_partition_metrics(slurm_cluster: &str) -> Result<(), Box<dyn Error>> {
+    let stdout = run_command(
+        "sinfo",
+        &[
+            "--noheader".to_string(),
+            "--Format=Cluster,Partition,NodeHost,StateLong,CPUsState".to_string(),
+            format!("--clusters={}", slurm_cluster),
+        ],
+    )?;
+    update_partition_metrics_from_output(&stdout)
+}
+
+// Build the partition metrics from sinfo output, series of partitions or states no longer
+// reported are removed
+fn update_partition_metrics_from_output(stdout: &str) -> Result<(), Box<dyn Error>> {
     // HashMap of
     //  <cluster>: {
     //   <partition>: {
@@ -169,54 +393,41 @@ pub fn update_partition_metrics(slurm_cluster: &str) -> Result<(), Box<dyn Error
     // }
     let mut cluster_partition_states: HashMap<String, HashMap<String, HashMap<String, i64>>> =
         HashMap::new();
+    // HashMap of
+    //  <cluster>: {
+    //   <partition>: (<allocated>, <idle>, <other>, <total>),
+    // }
+    let mut cluster_partition_cpus: HashMap<String, HashMap<String, CpuStates>> = HashMap::new();
 
-    env::set_var("LANG", "C");
-    debug!("slurm.rs:update_partition_metrics: running external command: sinfo --noheader --Format=Cluster,Partition,NodeHost,StateLong --clusters={}", slurm_cluster);
-    let sinfo = Command::new("sinfo")
-        .arg("--noheader")
-        .arg("--Format=Cluster,Partition,NodeHost,StateLong")
-        .arg(format!("--clusters={}", slurm_cluster))
-        .output()?;
-
-    let rc = match sinfo.status.code() {
-        Some(v) => v,
-        None => {
-            bail!("Can't get return code of sinfo command");
-        }
-    };
-    debug!(
-        "slurm.rs:update_partition_metrics: external command finished with exit code {}",
-        rc
-    );
-
-    if !sinfo.status.success() {
-        bail!("sinfo command exited with non-normal exit code {}", rc);
-    }
-
-    let stdout = String::from_utf8(sinfo.stdout)?;
     for line in stdout.lines() {
         debug!(
-            "slurm.rs:update_partition_metrics: Processing line: {}",
+            "slurm.rs:update_partition_metrics_from_output: Processing line: {}",
             line
         );
-        let (c, p, _, s) = match split_part_state_line(line) {
+        let (c, p, _, s, cpus) = match split_part_state_line(line) {
             Some(v) => v,
             None => bail!(
-                "Can't extract tuple cluster, partition, hostname and state from output '{}'",
+                "Can't extract tuple cluster, partition, hostname, state and CPU states from output '{}'",
                 line
             ),
         };
-        let cluster = cluster_partition_states
-            .entry(c)
-            .or_insert_with(HashMap::<String, HashMap<String, i64>>::new);
-        let partition = cluster.entry(p).or_insert_with(HashMap::<String, i64>::new);
+        let cluster = cluster_partition_cpus.entry(c.clone()).or_default();
+        let partition = cluster.entry(p.clone()).or_insert((0, 0, 0, 0));
+        partition.0 += cpus.0;
+        partition.1 += cpus.1;
+        partition.2 += cpus.2;
+        partition.3 += cpus.3;
+
+        let cluster = cluster_partition_states.entry(c).or_default();
+        let partition = cluster.entry(p).or_default();
         *partition.entry(s).or_insert(0) += 1;
     }
+    exporter::PARTITIONS.reset();
     for (clu, cpart) in cluster_partition_states.iter() {
         for (part, pstate) in cpart.iter() {
             for (state, count) in pstate.iter() {
                 debug!(
-                    "slurm.rs:update_partition_metrics: Setting PARTITIONS {} {} {} -> {}",
+                    "slurm.rs:update_partition_metrics_from_output: Setting PARTITIONS {} {} {} -> {}",
                     clu, part, state, *count
                 );
                 exporter::PARTITIONS
@@ -225,25 +436,175 @@ pub fn update_partition_metrics(slurm_cluster: &str) -> Result<(), Box<dyn Error
             }
         }
     }
+
+    exporter::PARTITION_CPUS_ALLOCATED.reset();
+    exporter::PARTITION_CPUS_IDLE.reset();
+    exporter::PARTITION_CPUS_OTHER.reset();
+    exporter::PARTITION_CPUS_TOTAL.reset();
+    for (clu, cpart) in cluster_partition_cpus.iter() {
+        for (part, (alloc, idle, other, total)) in cpart.iter() {
+            debug!(
+                "slurm.rs:update_partition_metrics_from_output: Setting PARTITION_CPUS_* {} {} -> {}/{}/{}/{}",
+                clu, part, alloc, idle, other, total
+            );
+            exporter::PARTITION_CPUS_ALLOCATED
+                .with_label_values(&[clu, part])
+                .set(*alloc);
+            exporter::PARTITION_CPUS_IDLE
+                .with_label_values(&[clu, part])
+                .set(*idle);
+            exporter::PARTITION_CPUS_OTHER
+                .with_label_values(&[clu, part])
+                .set(*other);
+            exporter::PARTITION_CPUS_TOTAL
+                .with_label_values(&[clu, part])
+                .set(*total);
+        }
+    }
     Ok(())
 }
 
-fn split_part_state_line(s: &str) -> Option<(String, String, String, String)> {
-    let (cluster, remain) = match s.split_once(' ') {
-        Some(v) => v,
-        None => return None,
-    };
-    let remain = remain.trim();
-    let (partition, remain) = match remain.split_once(' ') {
-        Some(v) => v,
-        None => return None,
-    };
-    let remain = remain.trim();
-    let (host, remain) = match remain.split_once(' ') {
-        Some(v) => v,
-        None => return None,
-    };
-    let state = remain.trim();
+pub struct NodeData {
+    pub cluster: String,
+    pub node: String,
+    pub partition: String,
+    pub state: String,
+    pub cpus_allocated: i64,
+    pub cpus_idle: i64,
+    pub cpus_other: i64,
+    pub cpus_total: i64,
+    pub memory: Option<i64>,
+    pub memory_allocated: Option<i64>,
+    pub memory_free: Option<i64>,
+    pub cpu_load: Option<f64>,
+}
+
+pub fn update_node_metrics(slurm_cluster: &str) -> Result<(), Box<dyn Error>> {
+    let nodes = fetch_node_data(slurm_cluster)?;
+
+    exporter::NODE_STATE.reset();
+    exporter::NODE_CPUS_ALLOCATED.reset();
+    exporter::NODE_CPUS_IDLE.reset();
+    exporter::NODE_CPUS_OTHER.reset();
+    exporter::NODE_CPUS_TOTAL.reset();
+    exporter::NODE_MEMORY.reset();
+    exporter::NODE_MEMORY_ALLOCATED.reset();
+    exporter::NODE_MEMORY_FREE.reset();
+    exporter::NODE_CPU_LOAD.reset();
+
+    for n in nodes.iter() {
+        debug!(
+            "slurm.rs:update_node_metrics: Setting node metrics for {} {} {}",
+            n.cluster, n.partition, n.node
+        );
+        let labels = [n.cluster.as_str(), n.node.as_str(), n.partition.as_str()];
+
+        exporter::NODE_STATE
+            .with_label_values(&[&n.cluster, &n.node, &n.partition, &n.state])
+            .set(1);
+        exporter::NODE_CPUS_ALLOCATED
+            .with_label_values(&labels)
+            .set(n.cpus_allocated);
+        exporter::NODE_CPUS_IDLE
+            .with_label_values(&labels)
+            .set(n.cpus_idle);
+        exporter::NODE_CPUS_OTHER
+            .with_label_values(&labels)
+            .set(n.cpus_other);
+        exporter::NODE_CPUS_TOTAL
+            .with_label_values(&labels)
+            .set(n.cpus_total);
+        if let Some(v) = n.memory {
+            exporter::NODE_MEMORY.with_label_values(&labels).set(v);
+        }
+        if let Some(v) = n.memory_allocated {
+            exporter::NODE_MEMORY_ALLOCATED
+                .with_label_values(&labels)
+                .set(v);
+        }
+        if let Some(v) = n.memory_free {
+            exporter::NODE_MEMORY_FREE.with_label_values(&labels).set(v);
+        }
+        if let Some(v) = n.cpu_load {
+            exporter::NODE_CPU_LOAD.with_label_values(&labels).set(v);
+        }
+    }
+    Ok(())
+}
+
+fn fetch_node_data(slurm_cluster: &str) -> Result<Vec<NodeData>, Box<dyn Error>> {
+    let mut result: Vec<NodeData> = Vec::new();
+
+    let stdout = run_command(
+        "sinfo",
+        &[
+            "--noheader".to_string(),
+            "--Node".to_string(),
+            "--Format=Cluster,NodeHost,Partition,StateLong,CPUsState,Memory,AllocMem,FreeMem,CPUsLoad"
+                .to_string(),
+            format!("--clusters={}", slurm_cluster),
+        ],
+    )?;
+    for line in stdout.lines() {
+        debug!("slurm.rs:fetch_node_data: Processing line: {}", line);
+        let node = match split_node_line(line) {
+            Some(v) => v,
+            None => bail!("Can't extract node data from output '{}'", line),
+        };
+        result.push(node);
+    }
+    Ok(result)
+}
+
+fn split_node_line(s: &str) -> Option<NodeData> {
+    let fields: Vec<&str> = s.split_whitespace().collect();
+    if fields.len() != 9 {
+        return None;
+    }
+
+    let (cpus_allocated, cpus_idle, cpus_other, cpus_total) = split_cpus_state(fields[4])?;
+
+    Some(NodeData {
+        cluster: fields[0].to_string(),
+        node: fields[1].to_string(),
+        // * marks the default partition
+        partition: fields[2].trim_end_matches('*').to_string(),
+        state: fields[3].to_string(),
+        cpus_allocated,
+        cpus_idle,
+        cpus_other,
+        cpus_total,
+        // sinfo reports memory in MiB
+        memory: fields[5].parse::<i64>().ok().map(|v| v * 1024 * 1024),
+        memory_allocated: fields[6].parse::<i64>().ok().map(|v| v * 1024 * 1024),
+        memory_free: fields[7].parse::<i64>().ok().map(|v| v * 1024 * 1024),
+        cpu_load: fields[8].parse::<f64>().ok(),
+    })
+}
+
+// CPUsState is reported as <allocated>/<idle>/<other>/<total>
+type CpuStates = (i64, i64, i64, i64);
+
+fn split_cpus_state(s: &str) -> Option<CpuStates> {
+    let fields: Vec<i64> = s
+        .split('/')
+        .map(|v| v.parse::<i64>())
+        .collect::<Result<Vec<i64>, _>>()
+        .ok()?;
+    if fields.len() != 4 {
+        return None;
+    }
+    Some((fields[0], fields[1], fields[2], fields[3]))
+}
+
+type PartitionStateLine = (String, String, String, String, CpuStates);
+
+fn split_part_state_line(s: &str) -> Option<PartitionStateLine> {
+    let (cluster, remain) = s.split_once(' ')?;
+    let (partition, remain) = remain.trim().split_once(' ')?;
+    let (host, remain) = remain.trim().split_once(' ')?;
+    let (state, remain) = remain.trim().split_once(' ')?;
+    let cpus = split_cpus_state(remain.trim())?;
 
     // * marks the default partition
     let mut partition = partition;
@@ -258,55 +619,498 @@ fn split_part_state_line(s: &str) -> Option<(String, String, String, String)> {
         partition.to_string(),
         host.to_string(),
         state.to_string(),
+        cpus,
     ))
 }
 
-fn split_job_state_line(s: &str) -> Option<(String, String, String, i64, i64, i64)> {
-    let (cluster, remain) = match s.split_once(' ') {
-        Some(v) => v,
-        None => return None,
-    };
-    let remain = remain.trim();
-    let (partition, remain) = match remain.split_once(' ') {
-        Some(v) => v,
-        None => return None,
-    };
-    let remain = remain.trim();
-    let (state, remain) = match remain.split_once(' ') {
-        Some(v) => v,
-        None => return None,
-    };
-    let remain = remain.trim();
-    let (_nodes, remain) = match remain.split_once(' ') {
-        Some(v) => v,
-        None => return None,
-    };
-    let nodes = match _nodes.parse::<i64>() {
+fn split_job_state_line(s: &str) -> Option<JobData> {
+    let fields: Vec<&str> = s.split_whitespace().collect();
+    if fields.len() < 16 {
+        return None;
+    }
+    let nodes = match fields[3].parse::<i64>() {
         Ok(v) => v,
         Err(_) => return None,
     };
-    let remain = remain.trim();
-    let (_tasks, remain) = match remain.split_once(' ') {
-        Some(v) => v,
-        None => return None,
-    };
-    let tasks = match _tasks.parse::<i64>() {
+    let tasks = match fields[4].parse::<i64>() {
         Ok(v) => v,
         Err(_) => return None,
     };
-
-    let _cpus = remain.trim();
-    let cpus = match _cpus.parse::<i64>() {
+    let cpus = match fields[5].parse::<i64>() {
         Ok(v) => v,
         Err(_) => return None,
     };
 
-    Some((
-        cluster.to_string(),
-        partition.to_string(),
-        state.to_string(),
+    Some(JobData {
+        cluster: fields[0].to_string(),
+        partition: fields[1].to_string(),
+        state: fields[2].to_string(),
         nodes,
         tasks,
         cpus,
-    ))
+        user: fields[6].to_string(),
+        account: fields[7].to_string(),
+        qos: fields[8].to_string(),
+        submit_time: parse_timestamp(fields[9]),
+        eligible_time: parse_timestamp(fields[10]),
+        time_used: parse_duration(fields[11]),
+        time_limit: parse_duration(fields[12]),
+        // ArrayJobID is the job id for regular jobs
+        array_job_id: if fields[14] == "N/A" {
+            "N/A".to_string()
+        } else {
+            fields[13].to_string()
+        },
+        array_tasks: array_task_count(fields[14]),
+        reason: fields[15..].join(" "),
+    })
+}
+
+// Number of array tasks in squeue's ArrayTaskID field, e.g. 1-5000%10 or 1,3,5-11:2.
+// Regular jobs (N/A) and running array tasks count as a single task
+pub fn array_task_count(s: &str) -> i64 {
+    // %<n> limits the number of simultaneously running tasks
+    let ranges = match s.split_once('%') {
+        Some((r, _)) => r,
+        None => s,
+    };
+    let ranges = ranges.trim_start_matches('[').trim_end_matches(']');
+
+    let mut result: i64 = 0;
+    for range in ranges.split(',') {
+        let (range, step) = match range.split_once(':') {
+            Some((r, s)) => (r, s.parse::<i64>().unwrap_or(1).max(1)),
+            None => (range, 1),
+        };
+        result += match range.split_once('-') {
+            Some((first, last)) => match (first.parse::<i64>(), last.parse::<i64>()) {
+                (Ok(first), Ok(last)) if last >= first => (last - first) / step + 1,
+                _ => 0,
+            },
+            None => match range.parse::<i64>() {
+                Ok(_) => 1,
+                _ => 0,
+            },
+        };
+    }
+    result.max(1)
+}
+
+// Parse SLURM durations in seconds. SLURM reports durations as [D-]HH:MM:SS or, for
+// durations below an hour, as M:SS. Durations with days may omit minutes and seconds
+// (D-HH, D-HH:MM) and a single number denotes minutes. Special values like UNLIMITED,
+// INVALID, NONE or NOT_SET are returned as None
+pub fn parse_duration(s: &str) -> Option<i64> {
+    let (days, remain) = match s.split_once('-') {
+        Some((d, r)) => (Some(d.parse::<i64>().ok()?), r),
+        None => (None, s),
+    };
+    let parts = remain
+        .split(':')
+        .map(|v| v.parse::<i64>())
+        .collect::<Result<Vec<i64>, _>>()
+        .ok()?;
+
+    let seconds = match (days, parts.as_slice()) {
+        (Some(d), [h]) => d * 86400 + h * 3600,
+        (Some(d), [h, m]) => d * 86400 + h * 3600 + m * 60,
+        (Some(d), [h, m, s]) => d * 86400 + h * 3600 + m * 60 + s,
+        (None, [m]) => m * 60,
+        (None, [m, s]) => m * 60 + s,
+        (None, [h, m, s]) => h * 3600 + m * 60 + s,
+        _ => return None,
+    };
+    Some(seconds)
+}
+
+// SLURM reports timestamps as local time without timezone information, e.g. 2022-06-13T08:15:42.
+// Unset timestamps are reported as N/A, None or Unknown
+pub fn parse_timestamp(s: &str) -> Option<i64> {
+    let naive = chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").ok()?;
+    let local = chrono::Local.from_local_datetime(&naive).earliest()?;
+    Some(local.timestamp())
+}
+
+// Reasons like "ReqNodeNotAvail, UnavailableNodes:node[01-04]" embed node lists or other
+// job specific information, only keep the reason itself to keep cardinality bounded
+fn normalise_pending_reason(s: &str) -> String {
+    let reason = s.trim().trim_start_matches('(').trim_end_matches(')');
+    let reason = match reason.find([',', ':', '(']) {
+        Some(v) => &reason[..v],
+        None => reason,
+    };
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return "None".to_string();
+    }
+    reason.to_string()
+}
+
+// Split the one-line output of scontrol (e.g. "ReservationName=maint NodeCnt=4 ...")
+// into key/value pairs. Values can contain whitespaces (e.g. comments), fields without
+// a = are appended to the value of the previous key
+pub fn split_key_value_line(s: &str) -> HashMap<String, String> {
+    let mut result: HashMap<String, String> = HashMap::new();
+    let mut last_key: Option<String> = None;
+
+    for field in s.split_whitespace() {
+        match field.split_once('=') {
+            Some((k, v)) => {
+                result.insert(k.to_string(), v.to_string());
+                last_key = Some(k.to_string());
+            }
+            None => {
+                if let Some(k) = &last_key {
+                    if let Some(v) = result.get_mut(k) {
+                        v.push(' ');
+                        v.push_str(field);
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+// Commands like sdiag only accept a single cluster, expand the cluster list
+// (or "all") to the list of cluster names. Resolved names of "all" are cached for
+// CLUSTER_NAMES_CACHE_TIME seconds to avoid an additional sacctmgr call for every collector
+pub fn cluster_names(slurm_cluster: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    if slurm_cluster != constants::SLURM_CLUSTERS {
+        return Ok(slurm_cluster
+            .split(',')
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect());
+    }
+
+    let mut cache = match CLUSTER_NAMES.lock() {
+        Ok(v) => v,
+        Err(e) => e.into_inner(),
+    };
+    let now = chrono::Local::now().timestamp();
+    if let Some((resolved, names)) = cache.as_ref() {
+        if now - resolved < constants::CLUSTER_NAMES_CACHE_TIME {
+            return Ok(names.clone());
+        }
+    }
+
+    match resolve_cluster_names() {
+        Ok(v) => {
+            debug!(
+                "slurm.rs:cluster_names: Resolved cluster names {}",
+                v.join(",")
+            );
+            *cache = Some((now, v.clone()));
+            Ok(v)
+        }
+        // keep using the last known cluster names until they can be resolved again
+        Err(e) => match cache.as_ref() {
+            Some((_, names)) => {
+                warn!(
+                    "Can't resolve cluster names, using cached cluster names: {}",
+                    e
+                );
+                Ok(names.clone())
+            }
+            None => Err(e),
+        },
+    }
+}
+
+// Collect the data of every cluster with collect. A failing cluster (e.g. with an unreachable
+// slurmctld) must not hide the metrics of the other clusters, it is logged and skipped.
+// Only fails if no cluster could be collected
+pub fn collect_clusters<T, F>(
+    slurm_cluster: &str,
+    mut collect: F,
+) -> Result<Vec<(String, T)>, Box<dyn Error>>
+where
+    F: FnMut(&str) -> Result<T, Box<dyn Error>>,
+{
+    let mut result: Vec<(String, T)> = Vec::new();
+    let mut last_error: Option<Box<dyn Error>> = None;
+
+    for cluster in cluster_names(slurm_cluster)? {
+        match collect(&cluster) {
+            Ok(v) => result.push((cluster, v)),
+            Err(e) => {
+                warn!("Can't collect data of cluster {}, skipping: {}", cluster, e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    match last_error {
+        Some(e) if result.is_empty() => Err(e),
+        _ => Ok(result),
+    }
+}
+
+// The clusters are listed by slurmdbd, a slurmctld which is down must not prevent the
+// resolution (e.g. for reporting the controller state). Clusters without ControlHost have
+// never registered a slurmctld (or were decommissioned) and can't be queried
+fn resolve_cluster_names() -> Result<Vec<String>, Box<dyn Error>> {
+    let mut result: Vec<String> = Vec::new();
+    let stdout = run_command(
+        "sacctmgr",
+        &[
+            "--noheader".to_string(),
+            "--parsable2".to_string(),
+            "show".to_string(),
+            "cluster".to_string(),
+            "format=Cluster,ControlHost".to_string(),
+        ],
+    )?;
+    for line in stdout.lines() {
+        let (cluster, control_host) = match line.split_once('|') {
+            Some((c, h)) => (c.trim(), h.trim()),
+            None => bail!("Can't extract cluster from sacctmgr output '{}'", line),
+        };
+        if control_host.is_empty() {
+            debug!(
+                "slurm.rs:resolve_cluster_names: Skipping cluster {} without ControlHost",
+                cluster
+            );
+            continue;
+        }
+        if !cluster.is_empty() && !result.iter().any(|v| v == cluster) {
+            result.push(cluster.to_string());
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The job metrics are global and reset by every update, serialise the tests updating them
+    static JOB_METRICS_LOCK: Mutex<()> = Mutex::new(());
+
+    fn lock_job_metrics() -> std::sync::MutexGuard<'static, ()> {
+        match JOB_METRICS_LOCK.lock() {
+            Ok(v) => v,
+            Err(e) => e.into_inner(),
+        }
+    }
+
+    fn configuration() -> config::Configuration {
+        config::Configuration {
+            clusters: constants::SLURM_CLUSTERS.to_string(),
+            bitmask: constants::BITMASK_JOB_COUNT,
+            time_limit_threshold: constants::DEFAULT_TIME_LIMIT_THRESHOLD * 60,
+            background_interval: 0,
+            min_interval: 0,
+            collector_intervals: HashMap::new(),
+        }
+    }
+
+    fn job_line(partition: &str, state: &str) -> String {
+        format!(
+            "c1 {} {} 1 1 4 alice physics normal 2022-06-13T08:15:42 2022-06-13T08:15:42 0:00 1:00:00 100 N/A None",
+            partition, state
+        )
+    }
+
+    #[test]
+    fn test_vanished_job_series_are_removed() {
+        let _lock = lock_job_metrics();
+        let cfg = configuration();
+        let output_a = [
+            job_line("batch", "PENDING"),
+            job_line("batch", "PENDING"),
+            job_line("gpu", "RUNNING"),
+        ]
+        .join("\n");
+        let output_b = job_line("batch", "RUNNING");
+
+        update_job_metrics_from_output(&cfg, &output_a).unwrap();
+        assert_eq!(
+            exporter::JOBS_COUNT
+                .with_label_values(&["c1", "batch", "PENDING"])
+                .get(),
+            2
+        );
+
+        update_job_metrics_from_output(&cfg, &output_b).unwrap();
+        assert!(exporter::JOBS_COUNT
+            .remove_label_values(&["c1", "batch", "PENDING"])
+            .is_err());
+        assert!(exporter::JOBS_COUNT
+            .remove_label_values(&["c1", "gpu", "RUNNING"])
+            .is_err());
+        assert_eq!(
+            exporter::JOBS_COUNT
+                .with_label_values(&["c1", "batch", "RUNNING"])
+                .get(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_vanished_partition_series_are_removed() {
+        let output_a = "c1 batch* n01 mixed 4/28/0/32\nc1 batch* n02 drained 0/0/32/32\nc1 gpu n03 idle 0/8/0/8";
+        let output_b = "c1 batch* n01 idle 0/32/0/32";
+
+        update_partition_metrics_from_output(output_a).unwrap();
+        assert_eq!(
+            exporter::PARTITIONS
+                .with_label_values(&["c1", "batch", "drained"])
+                .get(),
+            1
+        );
+
+        update_partition_metrics_from_output(output_b).unwrap();
+        for (partition, state) in [("batch", "mixed"), ("batch", "drained"), ("gpu", "idle")] {
+            assert!(exporter::PARTITIONS
+                .remove_label_values(&["c1", partition, state])
+                .is_err());
+        }
+        assert_eq!(
+            exporter::PARTITIONS
+                .with_label_values(&["c1", "batch", "idle"])
+                .get(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_normalise_pending_reason() {
+        assert_eq!(
+            normalise_pending_reason("ReqNodeNotAvail, UnavailableNodes:node[01-04]"),
+            "ReqNodeNotAvail"
+        );
+        assert_eq!(normalise_pending_reason("(Priority)"), "Priority");
+        assert_eq!(normalise_pending_reason(""), "None");
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(
+            parse_duration("2-03:04:05"),
+            Some(2 * 86400 + 3 * 3600 + 4 * 60 + 5)
+        );
+        assert_eq!(parse_duration("03:04:05"), Some(3 * 3600 + 4 * 60 + 5));
+        assert_eq!(parse_duration("4:05"), Some(4 * 60 + 5));
+        assert_eq!(parse_duration("UNLIMITED"), None);
+        assert_eq!(parse_duration("INVALID"), None);
+    }
+
+    #[test]
+    fn test_jobs_running_near_time_limit() {
+        let _lock = lock_job_metrics();
+        let mut cfg = configuration();
+        cfg.bitmask = constants::BITMASK_JOB_RUNTIME;
+        let running = |partition: &str, used: &str, limit: &str| {
+            format!(
+                "c1 {} RUNNING 1 1 4 alice physics normal 2022-06-13T08:15:42 2022-06-13T08:15:42 {} {} 100 N/A None",
+                partition, used, limit
+            )
+        };
+        let output = [
+            // far from the time limit
+            running("runtime", "10:00", "1:00:00"),
+            // within the threshold of 15 minutes
+            running("runtime", "50:00", "1:00:00"),
+            // over the time limit
+            running("runtime", "1:10:00", "1:00:00"),
+            // jobs without time limit are never near their limit
+            running("runtime", "3-00:00:00", "UNLIMITED"),
+            running("unlimited", "10:00", "UNLIMITED"),
+        ]
+        .join("\n");
+
+        update_job_metrics_from_output(&cfg, &output).unwrap();
+        assert_eq!(
+            exporter::JOBS_RUNNING_NEAR_TIME_LIMIT
+                .with_label_values(&["c1", "runtime"])
+                .get(),
+            2
+        );
+        assert_eq!(
+            exporter::JOBS_RUNNING_NEAR_TIME_LIMIT
+                .with_label_values(&["c1", "unlimited"])
+                .get(),
+            0
+        );
+        assert_eq!(
+            exporter::JOBS_RUNNING_ELAPSED
+                .with_label_values(&["c1", "runtime"])
+                .get_sample_count(),
+            4
+        );
+    }
+
+    #[test]
+    fn test_failing_clusters_are_skipped() {
+        let result = collect_clusters("c1,c2,c3", |cluster| {
+            if cluster == "c2" {
+                bail!("slurmctld of {} is unreachable", cluster);
+            }
+            Ok(cluster.len())
+        })
+        .unwrap();
+        assert_eq!(result, vec![("c1".to_string(), 2), ("c3".to_string(), 2)]);
+
+        let result = collect_clusters("c1,c2", |cluster| -> Result<(), Box<dyn Error>> {
+            bail!("slurmctld of {} is unreachable", cluster)
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_array_task_count() {
+        assert_eq!(array_task_count("1-5000%10"), 5000);
+        assert_eq!(array_task_count("1,3,5-11:2"), 6);
+        assert_eq!(array_task_count("[12-14]"), 3);
+        assert_eq!(array_task_count("N/A"), 1);
+    }
+
+    #[test]
+    fn test_pending_array_tasks_are_counted_individually() {
+        let _lock = lock_job_metrics();
+        let mut cfg = configuration();
+        cfg.bitmask = constants::BITMASK_JOB_COUNT | constants::BITMASK_JOB_CPUS;
+        let pending = |array_job_id: &str, tasks: &str| {
+            format!(
+                "c1 arrays PENDING 1 1 4 alice physics normal 2022-06-13T08:15:42 2022-06-13T08:15:42 0:00 1:00:00 {} {} None",
+                array_job_id, tasks
+            )
+        };
+        // squeue splits the pending tasks of an array into several lines, e.g. after some
+        // tasks were held
+        let output = [
+            pending("200", "1-10%2"),
+            pending("200", "11,13"),
+            pending("300", "N/A"),
+        ]
+        .join("\n");
+
+        update_job_metrics_from_output(&cfg, &output).unwrap();
+        assert_eq!(
+            exporter::JOBS_COUNT
+                .with_label_values(&["c1", "arrays", "PENDING"])
+                .get(),
+            13
+        );
+        assert_eq!(
+            exporter::JOBS_CPUS
+                .with_label_values(&["c1", "arrays", "PENDING"])
+                .get(),
+            52
+        );
+        assert_eq!(
+            exporter::JOBS_ARRAY_JOBS_PENDING
+                .with_label_values(&["c1", "arrays"])
+                .get(),
+            1
+        );
+        assert_eq!(
+            exporter::JOBS_ARRAY_TASKS_PENDING
+                .with_label_values(&["c1", "arrays"])
+                .get(),
+            12
+        );
+    }
 }
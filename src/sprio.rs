@@ -0,0 +1,125 @@
+use crate::command;
+use crate::constants;
+use crate::exporter;
+use crate::slurm;
+
+use log::debug;
+use std::collections::HashMap;
+use std::error::Error;
+
+// Priority components as reported by sprio, in the order of the format string
+const PRIORITY_COMPONENTS: &[&str] = &[
+    "total",
+    "age",
+    "fairshare",
+    "jobsize",
+    "partition",
+    "qos",
+    "tres",
+];
+
+pub fn update_priority_metrics(slurm_cluster: &str) -> Result<(), Box<dyn Error>> {
+    // HashMap of
+    //  [<cluster>, <partition>, <component>]: [<weighted priority>, ...]
+    let mut priorities: HashMap<Vec<String>, Vec<f64>> = HashMap::new();
+
+    let collected = slurm::collect_clusters(slurm_cluster, |cluster| {
+        // %r: partition, %Y: priority, %A: age, %F: fairshare, %J: job size,
+        // %P: partition, %Q: QOS, %T: TRES (all weighted)
+        let stdout = command::run_command(
+            "sprio",
+            &[
+                "--noheader".to_string(),
+                "--format=%r|%Y|%A|%F|%J|%P|%Q|%T".to_string(),
+                format!("--clusters={}", cluster),
+            ],
+        )?;
+        let mut result: Vec<(String, Vec<f64>)> = Vec::new();
+        for line in stdout.lines() {
+            debug!(
+                "sprio.rs:update_priority_metrics: Processing line: {}",
+                line
+            );
+            if line.starts_with("CLUSTER:") {
+                continue;
+            }
+            match split_sprio_line(line) {
+                Some(v) => result.push(v),
+                None => bail!("Can't extract job priorities from output '{}'", line),
+            };
+        }
+        Ok(result)
+    })?;
+
+    for (cluster, jobs) in collected {
+        for (partition, values) in jobs {
+            for (component, value) in PRIORITY_COMPONENTS.iter().zip(values.iter()) {
+                priorities
+                    .entry(vec![
+                        cluster.clone(),
+                        partition.clone(),
+                        component.to_string(),
+                    ])
+                    .or_default()
+                    .push(*value);
+            }
+        }
+    }
+
+    exporter::JOBS_PRIORITY.reset();
+    for (labels, values) in priorities.iter_mut() {
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        for q in constants::PRIORITY_QUANTILES.iter() {
+            let value = quantile(values, *q);
+            let quantile = q.to_string();
+            debug!(
+                "sprio.rs:update_priority_metrics: Setting JOBS_PRIORITY {} {} -> {}",
+                labels.join(" "),
+                quantile,
+                value
+            );
+            exporter::JOBS_PRIORITY
+                .with_label_values(&[&labels[0], &labels[1], &labels[2], &quantile])
+                .set(value);
+        }
+    }
+    Ok(())
+}
+
+fn split_sprio_line(s: &str) -> Option<(String, Vec<f64>)> {
+    let fields: Vec<&str> = s.split('|').map(|v| v.trim()).collect();
+    if fields.len() != PRIORITY_COMPONENTS.len() + 1 {
+        return None;
+    }
+
+    let mut values: Vec<f64> = Vec::new();
+    for v in fields[1..fields.len() - 1].iter() {
+        values.push(v.parse::<f64>().ok()?);
+    }
+    values.push(parse_tres_priority(fields[fields.len() - 1])?);
+
+    Some((fields[0].to_string(), values))
+}
+
+// The TRES priority is reported per TRES, e.g. cpu=12,mem=3
+fn parse_tres_priority(s: &str) -> Option<f64> {
+    if s.is_empty() {
+        return Some(0.0);
+    }
+    if let Ok(v) = s.parse::<f64>() {
+        return Some(v);
+    }
+
+    let mut result = 0.0;
+    for tres in s.split(',') {
+        let (_, value) = tres.split_once('=')?;
+        result += value.parse::<f64>().ok()?;
+    }
+    Some(result)
+}
+
+// Nearest-rank quantile of a sorted, non-empty list
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
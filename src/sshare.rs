@@ -0,0 +1,114 @@
+use crate::command;
+use crate::exporter;
+use crate::slurm;
+
+use log::debug;
+use std::error::Error;
+
+pub struct ShareData {
+    pub cluster: String,
+    pub account: String,
+    pub user: String,
+    pub raw_shares: Option<f64>,
+    pub norm_shares: Option<f64>,
+    pub raw_usage: Option<f64>,
+    pub effective_usage: Option<f64>,
+    pub fairshare: Option<f64>,
+}
+
+pub fn update_share_metrics(slurm_cluster: &str) -> Result<(), Box<dyn Error>> {
+    let shares: Vec<ShareData> = slurm::collect_clusters(slurm_cluster, |cluster| {
+        let stdout = command::run_command(
+            "sshare",
+            &[
+                "--all".to_string(),
+                "--long".to_string(),
+                "--parsable2".to_string(),
+                format!("--clusters={}", cluster),
+            ],
+        )?;
+        parse_sshare(cluster, &stdout)
+    })?
+    .into_iter()
+    .flat_map(|(_, v)| v)
+    .collect();
+
+    exporter::SHARE_RAW_SHARES.reset();
+    exporter::SHARE_NORM_SHARES.reset();
+    exporter::SHARE_RAW_USAGE.reset();
+    exporter::SHARE_EFFECTIVE_USAGE.reset();
+    exporter::SHARE_FAIRSHARE.reset();
+
+    for share in shares.iter() {
+        debug!(
+            "sshare.rs:update_share_metrics: Setting share metrics for {} {} {}",
+            share.cluster, share.account, share.user
+        );
+        let labels = [
+            share.cluster.as_str(),
+            share.account.as_str(),
+            share.user.as_str(),
+        ];
+        if let Some(v) = share.raw_shares {
+            exporter::SHARE_RAW_SHARES.with_label_values(&labels).set(v);
+        }
+        if let Some(v) = share.norm_shares {
+            exporter::SHARE_NORM_SHARES
+                .with_label_values(&labels)
+                .set(v);
+        }
+        if let Some(v) = share.raw_usage {
+            exporter::SHARE_RAW_USAGE.with_label_values(&labels).set(v);
+        }
+        if let Some(v) = share.effective_usage {
+            exporter::SHARE_EFFECTIVE_USAGE
+                .with_label_values(&labels)
+                .set(v);
+        }
+        if let Some(v) = share.fairshare {
+            exporter::SHARE_FAIRSHARE.with_label_values(&labels).set(v);
+        }
+    }
+    Ok(())
+}
+
+// Parse the --parsable2 (|-separated) output of sshare. Columns are looked up by the header
+// line because the set of columns depends on the SLURM version and the priority plugin
+fn parse_sshare(cluster: &str, s: &str) -> Result<Vec<ShareData>, Box<dyn Error>> {
+    let mut result: Vec<ShareData> = Vec::new();
+    let mut header: Vec<String> = Vec::new();
+
+    for line in s.lines() {
+        debug!("sshare.rs:parse_sshare: Processing line: {}", line);
+        let fields: Vec<&str> = line.split('|').map(|v| v.trim()).collect();
+
+        if header.is_empty() {
+            if fields.first() == Some(&"Account") {
+                header = fields.iter().map(|v| v.to_string()).collect();
+            }
+            continue;
+        }
+
+        if fields.len() < header.len() {
+            bail!("Can't extract share data from output '{}'", line);
+        }
+
+        let field = |name: &str| -> Option<&str> {
+            let idx = header.iter().position(|v| v == name)?;
+            Some(fields[idx])
+        };
+        let number = |name: &str| -> Option<f64> { field(name)?.parse::<f64>().ok() };
+
+        result.push(ShareData {
+            cluster: cluster.to_string(),
+            account: field("Account").unwrap_or_default().to_string(),
+            user: field("User").unwrap_or_default().to_string(),
+            raw_shares: number("RawShares"),
+            norm_shares: number("NormShares"),
+            raw_usage: number("RawUsage"),
+            effective_usage: number("EffectvUsage"),
+            fairshare: number("FairShare"),
+        });
+    }
+    Ok(result)
+}
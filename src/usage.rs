@@ -18,9 +18,20 @@ Public License Version 3. (http://www.gnu.org/copyleft/gpl.html)
 pub fn show_usage() {
     show_version();
     println!(
-        "Usage {} [-C|--no-job-cpus] [-D|--debug] [-J|--no-job-count] [-N|--no-job-nodes] [-T|--no-job-tasks]
-        [-V|--version] [-c <cluster>,...|--cluster=<cluster>,...] [-h|--help]
-        [-l <addr>|--listen=<addr>] [-q|--quiet]
+        "Usage {} [-A|--accounting] [-C|--no-job-cpus] [-D|--debug] [-E|--no-job-runtime]
+        [-F|--no-fairshare] [-G|--no-gres] [-I <seconds>|--min-interval=<seconds>]
+        [-J|--no-job-count] [-L|--no-licenses] [-N|--no-job-nodes] [-P|--no-partitions]
+        [-R|--no-pending-reasons] [-S|--no-sdiag] [-T|--no-job-tasks] [-V|--version]
+        [-W|--no-pending-wait] [-b <seconds>|--background-interval=<seconds>]
+        [-c <cluster>,...|--cluster=<cluster>,...] [-d|--no-node-reasons] [-h|--help]
+        [-j <label>,...|--job-labels=<label>,...] [-l <addr>|--listen=<addr>]
+        [-m|--no-controllers] [-n|--no-nodes] [-o <seconds>|--command-timeout=<seconds>]
+        [-p|--no-priority] [-q|--quiet] [-r|--no-reservations]
+        [-s|--no-partition-config] [-t <minutes>|--time-limit-threshold=<minutes>]
+        [-u <collector>=<seconds>,...|--collector-intervals=<collector>=<seconds>,...]
+
+    -A                      Export number of finished jobs and consumed CPU time
+    --accounting            from SLURM accounting (sacct)
 
     -C                      Don't export number of allocated or requested CPUs for jobs
     --no-job-cpus
@@ -28,34 +39,111 @@ pub fn show_usage() {
     -D                      Enable debug output
     --debug
 
+    -E                      Don't export elapsed time and time limit usage of
+    --no-job-runtime        running jobs
+
+    -F                      Don't export fairshare information (sshare)
+    --no-fairshare
+
+    -G                      Don't export configured, allocated and requested
+    --no-gres               generic resources (GRES)
+
+    -I <seconds>            Scrapes within <seconds> of the last collection are served
+    --min-interval=<seconds>
+                            from the result of the last collection instead of
+                            starting a new collection. Ignored for background
+                            collection. Default: collect metrics for every scrape
+
     -J                      Don't export number of jobs
     --no-job-count
 
+    -L                      Don't export license usage (scontrol, sacctmgr)
+    --no-licenses
+
     -N                      Don't export number of allocated or requested nodes for jobs
     --no-job-nodes
 
-    -P                      Don't export SLURM partition states
+    -P                      Don't export SLURM partition states and CPUs
     --no-partitions
 
+    -R                      Don't export number of pending jobs, requested CPUs
+    --no-pending-reasons    and nodes by pending reason
+
+    -S                      Don't export scheduler and RPC statistics (sdiag)
+    --no-sdiag
+
     -T                      Don't export number of allocated or requested tasks for jobs
     --no-job-tasks
 
     -V                      Show version information
     --version
 
+    -W                      Don't export wait times of pending jobs
+    --no-pending-wait
+
+    -b <seconds>            Collect metrics in the background every <seconds> and
+    --background-interval=<seconds>
+                            serve the result of the last collection for scrapes.
+                            Default: collect metrics for every scrape
+
     -c <cluster>,...        Export metrics for comma separated list of clusters
     --cluster=<cluster>,... Default: export data for all SLURM clusters
 
+    -d                      Don't export reason, user and age of drained, down or
+    --no-node-reasons       failed nodes
+
     -h                      Show help text
     --help
 
+    -j <label>,...          Comma separated list of labels of job metrics in addition
+    --job-labels=<label>,.. to cluster and state. Supported labels are account,
+                            partition, qos and user. Default: {}
+
     -l <addr>               Address to listen for Prometheus scrape requests
     --listen=<addr>         Default: {}
 
+    -m                      Don't export availability of SLURM controllers and the
+    --no-controllers        duration of scontrol ping
+
+    -n                      Don't export per-node metrics
+    --no-nodes
+
+    -o <seconds>            Timeout for SLURM commands, commands running longer
+    --command-timeout=<seconds>
+                            are killed. Default: {}
+
+    -p                      Don't export priorities of pending jobs (sprio)
+    --no-priority
+
     -q                      Quiet operation. Only warning and error messages
     --quiet                 are logged
+
+    -r                      Don't export reservations and maintenance windows
+    --no-reservations
+
+    -s                      Don't export partition configuration, limits and
+    --no-partition-config   configured partition states
+
+    -t <minutes>            Count running jobs within <minutes> of their time limit
+    --time-limit-threshold=<minutes>
+                            Default: {}
+
+    -u <collector>=<seconds>,...
+    --collector-intervals=<collector>=<seconds>,...
+                            Comma separated list of refresh intervals of collectors.
+                            Collectors are only run if their interval has elapsed,
+                            otherwise the result of their last run is served.
+                            Supported collectors are accounting, controllers, gres,
+                            jobs, licenses, node_reasons, nodes, partition_config,
+                            partitions, priority, reservations, sdiag and sshare.
+                            Default: run all collectors on every collection,
+                            accounting every {} seconds
 ",
         constants::NAME,
-        constants::DEFAULT_LISTEN_ADDRESS
+        constants::DEFAULT_JOB_LABELS,
+        constants::DEFAULT_LISTEN_ADDRESS,
+        constants::DEFAULT_COMMAND_TIMEOUT,
+        constants::DEFAULT_TIME_LIMIT_THRESHOLD,
+        constants::DEFAULT_ACCOUNTING_INTERVAL
     );
 }